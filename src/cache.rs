@@ -0,0 +1,91 @@
+use lru::LruCache;
+use std::{
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(256).unwrap();
+
+/// The queried name, along with a hash of the configuration of the resolvers
+pub(crate) type Key = (String, u64);
+
+struct Cache {
+    entries: LruCache<Key, Entry>,
+    min_ttl: Option<Duration>,
+    max_ttl: Option<Duration>,
+}
+
+struct Entry {
+    ips: Vec<IpAddr>,
+    valid_until: Instant,
+}
+
+/// Set the maximum number of hosts kept in the process-wide cache used by
+/// [`HickoryToSocketAddrs::cached`](crate::HickoryToSocketAddrs::cached).
+///
+/// The default size is 256. When shrinking the cache, the least recently used entries are
+/// evicted.
+pub fn set_cache_size(size: NonZeroUsize) {
+    cache().entries.resize(size);
+}
+
+/// Set the minimum TTL used to decide how long entries are kept in the process-wide cache.
+///
+/// Records with a shorter TTL are cached for `min_ttl` instead, which avoids cache thrashing.
+/// This only affects the cache, not the result of the lookups themselves.
+pub fn set_cache_min_ttl(min_ttl: Duration) {
+    cache().min_ttl = Some(min_ttl);
+}
+
+/// Set the maximum TTL used to decide how long entries are kept in the process-wide cache.
+///
+/// Records with a longer TTL are cached for `max_ttl` instead, which avoids serving stale
+/// results. This only affects the cache, not the result of the lookups themselves.
+pub fn set_cache_max_ttl(max_ttl: Duration) {
+    cache().max_ttl = Some(max_ttl);
+}
+
+pub(crate) fn get(key: &Key) -> Option<Vec<IpAddr>> {
+    let mut cache = cache();
+    let entry = cache.entries.get(key)?;
+    if entry.valid_until > Instant::now() {
+        return Some(entry.ips.clone());
+    }
+    cache.entries.pop(key);
+    None
+}
+
+pub(crate) fn insert(key: Key, ips: Vec<IpAddr>, valid_until: Instant) {
+    let mut cache = cache();
+    let now = Instant::now();
+    let mut ttl = valid_until.saturating_duration_since(now);
+    if let Some(min_ttl) = cache.min_ttl {
+        ttl = ttl.max(min_ttl);
+    }
+    if let Some(max_ttl) = cache.max_ttl {
+        ttl = ttl.min(max_ttl);
+    }
+    cache.entries.put(
+        key,
+        Entry {
+            ips,
+            valid_until: now + ttl,
+        },
+    );
+}
+
+fn cache() -> MutexGuard<'static, Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            Mutex::new(Cache {
+                entries: LruCache::new(DEFAULT_CACHE_SIZE),
+                min_ttl: None,
+                max_ttl: None,
+            })
+        })
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,58 @@
+use crate::{
+    AnyResolver, HickorySocketAddrs, HickoryToSocketAddrs, HickoryToSocketAddrsError, erase,
+};
+use hickory_resolver::{Resolver, name_server::ConnectionProvider};
+use hyper_util::client::legacy::connect::dns::Name;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+/// DNS resolver for `hyper_util`'s `HttpConnector`, backed by `hickory-dns`
+///
+/// ```rust
+/// use hickory_to_socket_addrs::HickoryResolverService;
+/// use hyper_util::client::legacy::connect::HttpConnector;
+///
+/// let connector = HttpConnector::new_with_resolver(HickoryResolverService::new());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HickoryResolverService {
+    resolver: Option<Arc<dyn AnyResolver>>,
+}
+
+impl HickoryResolverService {
+    /// Create a `HickoryResolverService` using the process-wide default resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a `HickoryResolverService` using the given resolver, whatever its connection provider.
+    pub fn with_resolver<P: ConnectionProvider>(resolver: Arc<Resolver<P>>) -> Self {
+        Self {
+            resolver: Some(erase(resolver)),
+        }
+    }
+}
+
+impl Service<Name> for HickoryResolverService {
+    type Response = HickorySocketAddrs;
+    type Error = HickoryToSocketAddrsError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        // hyper sets the port itself
+        let target = HickoryToSocketAddrs {
+            resolver: self.resolver.clone(),
+            ..HickoryToSocketAddrs::new(name.as_str().to_owned(), 0)
+        };
+        Box::pin(async move { target.lookup().await })
+    }
+}
@@ -9,7 +9,48 @@
 //! can block or has a lot of other known issues.
 //!
 //! If this is run in a `tokio` context, we use it, otherwise we spawn a new `tokio` runtime to
-//! perform the query.
+//! perform the query. Calling `to_socket_addrs` from async code is supported, but blocks the
+//! current thread for the duration of the query. From a current thread runtime, such a lookup
+//! can't reuse the connections of the resolver, which may be driven by the blocked runtime, and
+//! thus goes through a fresh copy of it, bypassing its cache. Only resolvers using the tokio
+//! connection provider can be copied: other ones are used as is, and a lookup through one whose
+//! connections are driven by the blocked runtime hangs until the blocking timeout expires.
+//!
+//! Unless a resolver is explicitly provided through [`HickoryToSocketAddrs::with_resolver`], a
+//! process-wide default resolver is lazily built from the system configuration and shared by all
+//! the lookups, so that hickory's cache survives between calls. It can instead be configured once
+//! at startup through [`init_global_resolver`].
+//!
+//! ## Features
+//!
+//! The items provided by these features are only documented when they are enabled, as they are
+//! on docs.rs.
+//!
+//! - `cache`: enable caching the resolved addresses through `HickoryToSocketAddrs::cached`
+//! - `dnssec`: enable DNSSEC validation through
+//!   `HickoryToSocketAddrsBuilder::validate_dnssec`
+//! - `doh`: enable DNS-over-HTTPS through `HickoryToSocketAddrs::with_doh`
+//! - `dot`: enable DNS-over-TLS through `HickoryToSocketAddrs::with_dot`
+//! - `hyper`: provide `HickoryResolverService`, a DNS resolver for `hyper_util`'s
+//!   `HttpConnector`
+//! - `mdns`: resolve the `.local` names through multicast DNS instead of the configured name
+//!   servers. This requires multicast to be allowed on the local network, and only uses IPv4
+//!   multicast
+//! - `no-ipv6`: only ever query and return IPv4 addresses, whatever the resolver options, for
+//!   targets without IPv6 connectivity
+//! - `reqwest`: provide `HickoryReqwestResolver`, a DNS resolver for `reqwest`
+//! - `serde`: implement `Serialize` and `Deserialize` for `HickoryToSocketAddrs<String>`, using
+//!   the `host:port` form
+//! - `stream`: provide `HickoryToSocketAddrs::stream`, yielding the resolved addresses as a
+//!   `futures` `Stream`
+//! - `tower`: provide `HickoryLookupService`, a generic `tower` service resolving
+//!   `HickoryToSocketAddrs<String>` targets
+//! - `tracing`: emit a `hickory_resolve` span, along with events, around each lookup. Besides
+//!   `host` and `port`, the span carries the `net.peer.name`, `net.peer.port`,
+//!   `dns.resolved_addresses` and `dns.duration_ms` fields, following the OpenTelemetry naming
+//!   conventions
+//! - `url`: allow creating a `HickoryToSocketAddrs` from an URL through
+//!   `HickoryToSocketAddrs::from_url`
 //!
 //! ## Example
 //!
@@ -24,16 +65,75 @@
 //! # Ok::<(), std::io::Error>(())
 //! ```
 
-use hickory_resolver::{Resolver, lookup_ip::LookupIpIntoIter};
+#[cfg(feature = "reqwest")]
+pub use self::reqwest::HickoryReqwestResolver;
+#[cfg(feature = "cache")]
+pub use cache::{set_cache_max_ttl, set_cache_min_ttl, set_cache_size};
+#[cfg(feature = "hyper")]
+pub use hyper::HickoryResolverService;
+pub use records::{Naptr, lookup_cname, lookup_naptr, lookup_txt, reverse_lookup};
+#[cfg(feature = "tower")]
+pub use tower::HickoryLookupService;
+
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "hyper")]
+mod hyper;
+#[cfg(feature = "mdns")]
+mod mdns;
+mod records;
+#[cfg(feature = "reqwest")]
+mod reqwest;
+mod rfc6724;
+#[cfg(feature = "tower")]
+mod tower;
+
+use futures_util::future::{self, AbortHandle, BoxFuture};
+use hickory_resolver::{
+    ResolveError, Resolver, ResolverBuilder, TokioResolver,
+    config::{
+        LookupIpStrategy, NameServerConfig, NameServerConfigGroup, ResolverConfig, ResolverOpts,
+    },
+    lookup::Lookup,
+    lookup_ip::LookupIp,
+    name_server::{ConnectionProvider, TokioConnectionProvider},
+    proto::{
+        ProtoError, ProtoErrorKind,
+        op::Query,
+        rr::{RData, RecordType, rdata::SRV},
+    },
+    system_conf::read_system_conf,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 use std::{
-    fmt,
-    future::Future,
+    any::Any,
+    cmp::{Ordering, Reverse},
+    collections::HashSet,
+    error, fmt,
+    future::{Future, IntoFuture, pending, poll_fn},
+    hash::{Hash, Hasher},
     io,
-    net::{SocketAddr, ToSocketAddrs},
+    net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
+    ops::Deref,
+    panic,
+    path::PathBuf,
+    pin::{Pin, pin},
     str::FromStr,
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError,
+        atomic::{self, AtomicBool, AtomicUsize},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+    vec,
+};
+use tokio::{
+    net::TcpStream,
+    runtime::{Handle, Runtime, RuntimeFlavor},
 };
 
-pub use hickory_resolver::IntoName;
+pub use hickory_resolver::{IntoName, Name, config, proto::xfer::Protocol};
 
 /// Wrapper around host and port to resolve to `SocketAddr` through `hickory-dns`
 ///
@@ -47,77 +147,2848 @@ pub use hickory_resolver::IntoName;
 ///     .collect::<Vec<_>>();
 /// # Ok::<(), std::io::Error>(())
 /// ```
-#[derive(Debug)]
+///
+/// International domain names, such as `münchen.de`, are supported: `hickory-dns` converts them
+/// to their punycode form (`xn--mnchen-3ya.de`) before issuing the query.
+///
+/// Equality, ordering and hashing only take the target into account (host, port and whether this
+/// is an SRV target, in this order), not the resolver nor the lookup options.
+#[derive(Clone, Debug)]
 pub struct HickoryToSocketAddrs<T: IntoName + Clone> {
     host: T,
     port: u16,
+    resolver: Option<Arc<dyn AnyResolver>>,
+    fallbacks: Vec<Arc<dyn AnyResolver>>,
+    timeout: Option<Duration>,
+    family: AddressFamily,
+    preferred_family: AddressFamily,
+    scope_id: Option<u32>,
+    rfc6724: bool,
+    round_robin: Option<Arc<AtomicUsize>>,
+    happy_eyeballs: bool,
+    dedup: bool,
+    max_results: Option<usize>,
+    shuffle: bool,
+    shuffle_seed: Option<u64>,
+    srv_seed: Option<u64>,
+    error_on_empty: bool,
+    srv: bool,
+    std_fallback: bool,
+    reject_zero_port: bool,
+    absolute: bool,
+    origin: Option<Name>,
+    on_complete: Option<OnComplete<T>>,
+    #[cfg(feature = "cache")]
+    cached: bool,
+    #[cfg(feature = "cache")]
+    cache_ttl_floor: Option<Duration>,
+    #[cfg(feature = "tracing")]
+    partial_results_warn: bool,
+}
+
+type OnCompleteFn<T> = dyn Fn(&HickoryToSocketAddrs<T>, Result<usize, &HickoryToSocketAddrsError>, Duration)
+    + Send
+    + Sync;
+
+struct OnComplete<T: IntoName + Clone>(Arc<OnCompleteFn<T>>);
+
+impl<T: IntoName + Clone> Clone for OnComplete<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: IntoName + Clone> fmt::Debug for OnComplete<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("OnComplete").finish()
+    }
 }
 
 impl<H: IntoName + Clone> HickoryToSocketAddrs<H> {
     /// Create a `HickoryToSocketAddrs` from split host and port components.
-    pub fn new(host: H, port: u16) -> Self {
-        Self { host, port }
+    ///
+    /// The lookup will go through the process-wide default resolver.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    /// use std::net::ToSocketAddrs;
+    ///
+    /// let socket_addrs = HickoryToSocketAddrs::new("www.rust-lang.org", 443)
+    ///     .to_socket_addrs()?
+    ///     .collect::<Vec<_>>();
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// This is a `const fn`, so that targets can be declared statically, e.g. in a table of
+    /// fallback targets. The host is only validated when performing the lookup.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// static TARGETS: &[HickoryToSocketAddrs<&'static str>] = &[
+    ///     HickoryToSocketAddrs::new("primary.example.com", 443),
+    ///     HickoryToSocketAddrs::new("backup.example.com", 443),
+    /// ];
+    /// ```
+    pub const fn new(host: H, port: u16) -> Self {
+        Self {
+            host,
+            port,
+            resolver: None,
+            fallbacks: Vec::new(),
+            timeout: None,
+            family: AddressFamily::Any,
+            preferred_family: AddressFamily::Any,
+            scope_id: None,
+            rfc6724: false,
+            round_robin: None,
+            happy_eyeballs: false,
+            dedup: false,
+            max_results: None,
+            shuffle: false,
+            shuffle_seed: None,
+            srv_seed: None,
+            error_on_empty: false,
+            srv: false,
+            std_fallback: false,
+            reject_zero_port: false,
+            absolute: false,
+            origin: None,
+            on_complete: None,
+            #[cfg(feature = "cache")]
+            cached: false,
+            #[cfg(feature = "cache")]
+            cache_ttl_floor: None,
+            #[cfg(feature = "tracing")]
+            partial_results_warn: false,
+        }
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, validating the host
+    /// right away instead of when performing the lookup.
+    ///
+    /// This fails with [`HickoryToSocketAddrsError::EmptyHost`] if the host is empty, and with
+    /// [`HickoryToSocketAddrsError::InvalidName`] if it is neither an IP address nor a valid DNS
+    /// name.
+    pub fn try_new(host: H, port: u16) -> Result<Self, HickoryToSocketAddrsError> {
+        let target = Self::new(host, port);
+        if target.host.to_ip().is_none() {
+            target.name()?;
+        }
+        Ok(target)
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through the given resolver.
+    ///
+    /// This allows several `HickoryToSocketAddrs` to share the same resolver (and thus its cache),
+    /// or to wrap an externally configured resolver, given either as is or behind an `Arc`. The
+    /// resolver can use any connection provider, e.g. one tunneling the queries through a proxy.
+    ///
+    /// Unlike one using the tokio connection provider, a resolver using another provider can't be
+    /// copied for blocking lookups performed from a current thread runtime. Its connections must
+    /// then not be driven by that runtime, or these lookups hang until the blocking timeout set
+    /// through [`set_blocking_timeout`] expires.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    /// use hickory_resolver::Resolver;
+    /// use std::net::ToSocketAddrs;
+    ///
+    /// let resolver = Resolver::builder_tokio()?.build();
+    /// let socket_addrs = HickoryToSocketAddrs::with_resolver("www.rust-lang.org", 443, resolver)
+    ///     .to_socket_addrs()?
+    ///     .collect::<Vec<_>>();
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_resolver<P: ConnectionProvider>(
+        host: H,
+        port: u16,
+        resolver: impl Into<Arc<Resolver<P>>>,
+    ) -> Self {
+        Self {
+            resolver: Some(erase(resolver)),
+            ..Self::new(host, port)
+        }
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through a chain of resolvers built from the given configurations, such as a primary
+    /// internal resolver and a public fallback.
+    ///
+    /// If a resolver fails, e.g. because its name servers are unreachable, the lookup is retried
+    /// with the next one. A negative answer (such as `NXDOMAIN`) stops the chain, so that queries
+    /// for internal names don't leak to the fallbacks. SRV lookups only use the first resolver.
+    pub fn with_resolver_chain(
+        host: H,
+        port: u16,
+        configs: &[ResolverConfig],
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        let mut resolvers = configs.iter().map(|config| {
+            build_resolver(Resolver::builder_with_config(
+                config.clone(),
+                TokioConnectionProvider::default(),
+            ))
+        });
+        let resolver = resolvers
+            .next()
+            .ok_or(HickoryToSocketAddrsError::NoNameServers)?;
+        Ok(Self {
+            fallbacks: resolvers.map(erase).collect(),
+            ..Self::with_resolver(host, port, resolver)
+        })
+    }
+
+    /// Create a `HickoryToSocketAddrs` for a service name such as `_xmpp-client._tcp.example.com`
+    ///
+    /// The lookup will be performed through DNS SRV records, which provide the port for each of
+    /// the targets.
+    pub fn from_srv(name: H) -> Self {
+        Self {
+            srv: true,
+            ..Self::new(name, 0)
+        }
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through the given name servers instead of the system ones.
+    ///
+    /// The queries are sent to the port of each address, which doesn't need to be 53, e.g. to
+    /// reach a local resolver listening on 5353.
+    ///
+    /// This is a shortcut for
+    /// [`HickoryToSocketAddrsBuilder::nameservers`](HickoryToSocketAddrsBuilder::nameservers).
+    pub fn with_nameservers(
+        host: H,
+        port: u16,
+        nameservers: &[SocketAddr],
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        Self::builder(host, port).nameservers(nameservers).build()
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through a resolver configured from the given `resolv.conf` file instead of the system one.
+    ///
+    /// Failing to read or parse the file is reported through
+    /// [`HickoryToSocketAddrsError::ResolvConf`].
+    #[cfg(unix)]
+    pub fn from_resolv_conf(
+        host: H,
+        port: u16,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        use hickory_resolver::system_conf::parse_resolv_conf;
+        use std::fs;
+
+        let path = path.as_ref();
+        let (config, options) = fs::read(path)
+            .and_then(|data| {
+                parse_resolv_conf(data)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            })
+            .map_err(|err| HickoryToSocketAddrsError::ResolvConf(path.to_owned(), err))?;
+        Self::builder(host, port)
+            .config(config)
+            .options(options)
+            .build()
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through DNS-over-HTTPS.
+    ///
+    /// `url` is the URL of the DoH server, such as `https://dns.google/dns-query`, and
+    /// `bootstrap_ips` are its IP addresses, avoiding the need to resolve its name first.
+    #[cfg(feature = "doh")]
+    pub fn with_doh(
+        host: H,
+        port: u16,
+        url: &str,
+        bootstrap_ips: &[IpAddr],
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        Self::builder(host, port)
+            .config(doh_config(url, bootstrap_ips)?)
+            .build()
+    }
+
+    /// Create a `HickoryToSocketAddrs` from split host and port components, performing the lookup
+    /// through DNS-over-TLS.
+    ///
+    /// `server_ip` is the IP address of the DoT server (the port defaults to 853 if it's 0) and
+    /// `server_name` is the name used to validate its certificate.
+    ///
+    /// This relies on hickory's `tls-ring` feature, enabled by the `dot` feature.
+    #[cfg(feature = "dot")]
+    pub fn with_dot(
+        host: H,
+        port: u16,
+        mut server_ip: SocketAddr,
+        server_name: &str,
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        if server_ip.port() == 0 {
+            server_ip.set_port(853);
+        }
+        let mut config = NameServerConfig::new(server_ip, Protocol::Tls);
+        config.tls_dns_name = Some(server_name.to_owned());
+        Self::builder(host, port)
+            .config(ResolverConfig::from_parts(
+                None,
+                Vec::new(),
+                NameServerConfigGroup::from(vec![config]),
+            ))
+            .build()
+    }
+
+    /// Create a `HickoryToSocketAddrsBuilder` to customize the resolver used for the lookup.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::{HickoryToSocketAddrs, config::ResolverConfig};
+    /// use std::net::ToSocketAddrs;
+    ///
+    /// let socket_addrs = HickoryToSocketAddrs::builder("www.rust-lang.org", 443)
+    ///     .config(ResolverConfig::cloudflare())
+    ///     .build()?
+    ///     .to_socket_addrs()?
+    ///     .collect::<Vec<_>>();
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn builder(host: H, port: u16) -> HickoryToSocketAddrsBuilder<H> {
+        HickoryToSocketAddrsBuilder {
+            host,
+            port,
+            config: None,
+            options: None,
+            overrides: OptionsOverrides::default(),
+            nameservers: None,
+            bind_addr: None,
+            protocol: None,
+            search_domains: None,
+        }
+    }
+
+    /// Bound the whole resolution attempt to the given duration.
+    ///
+    /// When it expires, a [`HickoryToSocketAddrsError::Timeout`] error is returned.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only yield the resolved addresses of the given family.
+    ///
+    /// This doesn't affect the queries sent to the name servers, only what is returned to the
+    /// caller.
+    pub fn address_family(mut self, family: AddressFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Order the addresses following the destination address selection rules of RFC 6724, given
+    /// the source addresses available on this host, as `getaddrinfo` does.
+    ///
+    /// By default, addresses are yielded in the order returned by hickory.
+    pub fn rfc6724(mut self, rfc6724: bool) -> Self {
+        self.rfc6724 = rfc6724;
+        self
+    }
+
+    /// Start yielding the addresses at an offset rotating on each lookup of this target, to spread
+    /// the connections across all of them.
+    ///
+    /// Unlike [`HickoryToSocketAddrs::shuffle`], this is a deterministic rotation, tracked by a
+    /// counter shared with the clones of this target. It is applied before
+    /// [`HickoryToSocketAddrs::max_results`].
+    pub fn round_robin(mut self, round_robin: bool) -> Self {
+        self.round_robin = round_robin.then(Arc::default);
+        self
+    }
+
+    /// Yield the addresses of the given family first, while still yielding the other ones
+    /// afterwards.
+    ///
+    /// The relative order of the addresses of each family is preserved. This is ignored when
+    /// [`HickoryToSocketAddrs::happy_eyeballs`] is enabled.
+    pub fn prefer_family(mut self, family: AddressFamily) -> Self {
+        self.preferred_family = family;
+        self
+    }
+
+    /// Set the scope ID of the yielded link-local IPv6 addresses (`fe80::/10`), such as the index
+    /// of the interface they are reachable through, so that they can be connected to.
+    ///
+    /// DNS answers don't carry any scope, and it cannot be derived from
+    /// [`HickoryToSocketAddrsBuilder::bind_addr`] which only holds an IP address, so link-local
+    /// addresses are yielded with a scope ID of 0 unless this is set. Other addresses are left
+    /// untouched.
+    ///
+    /// This is also set when parsing an IPv6 address with a zone, given either as an interface
+    /// name or index, such as `[fe80::1%eth0]:443`.
+    pub fn scope_id(mut self, scope_id: u32) -> Self {
+        self.scope_id = Some(scope_id);
+        self
+    }
+
+    /// Interleave IPv6 and IPv4 addresses, as recommended by RFC 8305, starting with the
+    /// [preferred family](HickoryToSocketAddrs::prefer_family), or with the family of the first
+    /// address if there is no preference.
+    ///
+    /// By default, addresses are yielded in the order returned by hickory.
+    pub fn happy_eyeballs(mut self, happy_eyeballs: bool) -> Self {
+        self.happy_eyeballs = happy_eyeballs;
+        self
+    }
+
+    /// Skip the addresses which have already been yielded, preserving the first-seen order.
+    ///
+    /// By default, duplicates returned by the resolver are yielded as is.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Yield at most `max_results` addresses.
+    ///
+    /// The limit is applied after filtering and deduplicating the addresses.
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Serve the lookups from a process-wide cache, keyed by host, whose entries expire with the
+    /// TTL of the records.
+    ///
+    /// On a cache miss, the lookup is performed as usual and its result gets inserted in the
+    /// cache. The size of the cache can be customized with [`set_cache_size`].
+    #[cfg(feature = "cache")]
+    pub fn cached(mut self, cached: bool) -> Self {
+        self.cached = cached;
+        self
+    }
+
+    /// Keep the addresses of this host in the cache for at least the given duration, even if the
+    /// records advertise a shorter TTL.
+    ///
+    /// Unlike [`set_cache_min_ttl`], this only applies to the entries inserted by this
+    /// `HickoryToSocketAddrs`. The entries are still bounded by [`set_cache_max_ttl`].
+    #[cfg(feature = "cache")]
+    pub fn cache_ttl_floor(mut self, floor: Duration) -> Self {
+        self.cache_ttl_floor = Some(floor);
+        self
+    }
+
+    /// Emit a warning when only one address family resolved while the query for the other one
+    /// failed, such as a timeout on the AAAA query, instead of silently returning the partial
+    /// results.
+    ///
+    /// hickory doesn't report the failed query, so the missing family is queried again to tell a
+    /// failure apart from a host which simply has no such records. This only applies with the
+    /// `Ipv4AndIpv6` lookup strategy.
+    #[cfg(feature = "tracing")]
+    pub fn partial_results_warn(mut self, warn: bool) -> Self {
+        self.partial_results_warn = warn;
+        self
+    }
+
+    /// Randomize the order of the yielded addresses, to spread the load across them.
+    ///
+    /// By default, addresses are yielded in the order returned by hickory.
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Randomize the order of the yielded addresses, using a RNG seeded with the given seed.
+    ///
+    /// This is mostly useful for reproducible tests.
+    pub fn shuffle_with_seed(mut self, seed: u64) -> Self {
+        self.shuffle = true;
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Seed the RNG used for the weighted selection of [`HickoryToSocketAddrs::lookup_srv_ordered`].
+    ///
+    /// This is mostly useful for reproducible tests.
+    pub fn srv_seed(mut self, seed: u64) -> Self {
+        self.srv_seed = Some(seed);
+        self
+    }
+
+    /// Retry through the system resolver (`std::net::ToSocketAddrs`) if hickory fails, e.g.
+    /// because it couldn't read the system configuration.
+    ///
+    /// Lookups failing because the host doesn't exist, because the name servers timed out or
+    /// because of [`HickoryToSocketAddrs::with_timeout`] don't trigger the fallback. If the fallback fails
+    /// too, the original error is returned. The fallback is logged when the `tracing` feature is
+    /// enabled.
+    pub fn with_std_fallback(mut self, std_fallback: bool) -> Self {
+        self.std_fallback = std_fallback;
+        self
+    }
+
+    /// Invoke the given callback after each lookup, with the target, the number of resolved
+    /// addresses or the error, and the time the lookup took.
+    ///
+    /// This allows feeding any metrics system. The callback isn't invoked for IP addresses, which
+    /// don't need any lookup.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// let target = HickoryToSocketAddrs::new("www.rust-lang.org", 443)
+    ///     .on_complete(|target, outcome, elapsed| println!("{target}: {outcome:?} in {elapsed:?}"));
+    /// ```
+    pub fn on_complete(
+        mut self,
+        on_complete: impl Fn(&Self, Result<usize, &HickoryToSocketAddrsError>, Duration)
+        + Send
+        + Sync
+        + 'static,
+    ) -> Self {
+        self.on_complete = Some(OnComplete(Arc::new(on_complete)));
+        self
+    }
+
+    /// Fail with [`HickoryToSocketAddrsError::InvalidPort`] instead of resolving if the port is 0,
+    /// which is almost always a configuration mistake for connection targets.
+    ///
+    /// By default, a port of 0 is accepted, e.g. for binding to an OS-assigned port. This doesn't
+    /// apply to SRV lookups, whose ports come from the records.
+    pub fn reject_zero_port(mut self, reject_zero_port: bool) -> Self {
+        self.reject_zero_port = reject_zero_port;
+        self
+    }
+
+    /// Treat the host as a fully qualified name, as if it ended with a dot, so that the search
+    /// domains are never appended to it.
+    ///
+    /// By default, a host without a trailing dot is relative: depending on the `ndots` option, the
+    /// search domains of the configuration may be tried for it.
+    pub fn absolute(mut self, absolute: bool) -> Self {
+        self.absolute = absolute;
+        self
+    }
+
+    /// Append the given origin to the host before querying it, e.g. to resolve `db` within
+    /// `svc.cluster.local`, ignoring the search domains of the configuration.
+    ///
+    /// The resulting name is treated as fully qualified. A host which is already fully qualified,
+    /// or an IP address, is left as is. If the resulting name isn't a valid DNS name, such as when
+    /// it is too long, the lookup fails with [`HickoryToSocketAddrsError::InvalidName`].
+    pub fn with_origin(mut self, origin: Name) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Return a [`HickoryToSocketAddrsError::NoAddresses`] error instead of an empty iterator
+    /// when no address is found, as `getaddrinfo` does.
+    ///
+    /// By default, an empty iterator is returned.
+    pub fn error_on_empty(mut self, error_on_empty: bool) -> Self {
+        self.error_on_empty = error_on_empty;
+        self
     }
 
     /// Perform DNS resolution and return iterator of SocketAddr using hickory-dns
-    pub async fn lookup(&self) -> io::Result<HickorySocketAddrs> {
-        Ok(HickorySocketAddrs(
-            Resolver::builder_tokio()?
-                .build()
-                .lookup_ip(self.host.clone())
-                .await?
-                .into_iter(),
+    ///
+    /// If the host is an IP address, it is returned as is, without performing any query.
+    /// Otherwise, it is validated first, failing with [`HickoryToSocketAddrsError::InvalidName`]
+    /// if it isn't a valid DNS name.
+    ///
+    /// If this was created through [`HickoryToSocketAddrs::from_srv`], this performs an SRV
+    /// lookup instead, as [`HickoryToSocketAddrs::lookup_srv`] does.
+    pub async fn lookup(&self) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        Ok(self.lookup_traced().await?.0)
+    }
+
+    /// Perform DNS resolution like [`HickoryToSocketAddrs::lookup`], also reporting whether the
+    /// addresses were served from the cache and how long the lookup took.
+    pub async fn lookup_detailed(&self) -> Result<LookupDetails, HickoryToSocketAddrsError> {
+        let start = Instant::now();
+        let (addrs, from_cache) = self.lookup_traced().await?;
+        Ok(LookupDetails {
+            addrs,
+            from_cache,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    async fn lookup_traced(&self) -> Result<(HickorySocketAddrs, bool), HickoryToSocketAddrsError> {
+        self.instrumented(self.lookup_addrs(), |(addrs, from_cache)| {
+            (addrs.inner.len(), *from_cache)
+        })
+        .await
+    }
+
+    /// Run the given lookup within a tracing span, attaching the target to its errors and
+    /// reporting it to the completion callback. The summary gives the number of resolved
+    /// addresses and whether they were served from the cache.
+    async fn instrumented<T>(
+        &self,
+        fut: impl Future<Output = Result<T, HickoryToSocketAddrsError>>,
+        summary: impl Fn(&T) -> (usize, bool),
+    ) -> Result<T, HickoryToSocketAddrsError> {
+        let start = self
+            .on_complete
+            .as_ref()
+            .filter(|_| self.srv || self.host.to_ip().is_none())
+            .map(|on_complete| (on_complete, Instant::now()));
+        #[cfg(feature = "tracing")]
+        let fut = traced(self.host_string(), self.port, fut, &summary);
+        let res = fut.await.map_err(|err| match err {
+            HickoryToSocketAddrsError::Resolve(err) => {
+                HickoryToSocketAddrsError::ResolveTarget(self.target_string(), err)
+            }
+            err => err,
+        });
+        if let Some((on_complete, start)) = start {
+            let outcome = res.as_ref().map(|res| summary(res).0);
+            (on_complete.0)(self, outcome, start.elapsed());
+        }
+        res
+    }
+
+    /// Perform DNS resolution and split the resolved addresses by family
+    ///
+    /// The order of the addresses within each family is preserved.
+    pub async fn lookup_grouped(&self) -> Result<GroupedAddrs, HickoryToSocketAddrsError> {
+        let mut grouped = GroupedAddrs::default();
+        for addr in self.lookup().await? {
+            match addr {
+                SocketAddr::V4(addr) => grouped.v4.push(addr),
+                SocketAddr::V6(addr) => grouped.v6.push(addr),
+            }
+        }
+        Ok(grouped)
+    }
+
+    /// Perform the A and AAAA queries concurrently, returning the addresses of the preferred family
+    /// as soon as they arrive, or those of the other family if the preferred one didn't answer
+    /// within the given delay.
+    ///
+    /// The preferred family is the one set through [`HickoryToSocketAddrs::prefer_family`],
+    /// defaulting to IPv6 as Happy Eyeballs does. If the query for a family fails, the addresses
+    /// of the other one are returned regardless of the delay. The timeout set through
+    /// [`HickoryToSocketAddrs::with_timeout`] bounds the whole operation, including the delay.
+    pub async fn resolve_preferred_family_or_wait(
+        &self,
+        delay: Duration,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        self.instrumented(self.lookup_preferred_family(delay), |addrs| {
+            (addrs.inner.len(), false)
+        })
+        .await
+    }
+
+    async fn lookup_preferred_family(
+        &self,
+        delay: Duration,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        self.check_port()?;
+        if let Some(addrs) = self.ip_literal() {
+            return self.non_empty(addrs);
+        }
+        let name = self.name()?;
+        let resolver = self.resolver()?;
+        // With a single family to query, or with the cache or mDNS answering both at once, there
+        // is nothing to race.
+        let single_family = cfg!(feature = "no-ipv6")
+            || self.family != AddressFamily::Any
+            || matches!(
+                resolver.options().ip_strategy,
+                LookupIpStrategy::Ipv4Only | LookupIpStrategy::Ipv6Only
+            );
+        #[cfg(feature = "cache")]
+        let single_family = single_family || self.cached;
+        #[cfg(feature = "mdns")]
+        let single_family = single_family || mdns::is_local(&name);
+        if self.srv || single_family {
+            return Ok(self.lookup_addrs().await?.0);
+        }
+        let family = |record_type| {
+            let name = name.clone();
+            async move {
+                let (res, last) = self
+                    .query_with_fallbacks(resolver, |resolver| {
+                        resolver.lookup(name.clone(), record_type)
+                    })
+                    .await;
+                res.map(|lookup| lookup.iter().filter_map(RData::ip_addr).collect::<Vec<_>>())
+                    .map_err(|err| resolve_error(last, err))
+            }
+        };
+        let (preferred, other) = match self.preferred_family {
+            AddressFamily::V4Only => (family(RecordType::A), family(RecordType::AAAA)),
+            _ => (family(RecordType::AAAA), family(RecordType::A)),
+        };
+        let ips = self
+            .timed(async {
+                let mut preferred = pin!(preferred);
+                let mut other = pin!(other);
+                let mut sleep = pin!(tokio::time::sleep(delay));
+                let (mut preferred_res, mut other_res) = (None, None);
+                poll_fn(|cx| {
+                    if preferred_res.is_none() {
+                        if let Poll::Ready(res) = preferred.as_mut().poll(cx) {
+                            preferred_res = Some(res);
+                        }
+                    }
+                    if other_res.is_none() {
+                        if let Poll::Ready(res) = other.as_mut().poll(cx) {
+                            other_res = Some(res);
+                        }
+                    }
+                    let expired = sleep.as_mut().poll(cx).is_ready();
+                    match (&preferred_res, &other_res) {
+                        (Some(Ok(_)), _) | (Some(Err(_)), Some(_)) => Poll::Ready(()),
+                        (_, Some(Ok(_))) if expired => Poll::Ready(()),
+                        _ => Poll::Pending,
+                    }
+                })
+                .await;
+                match (preferred_res, other_res) {
+                    (Some(Ok(ips)), _) | (_, Some(Ok(ips))) => Ok(ips),
+                    (Some(Err(err)), _) | (_, Some(Err(err))) => Err(err),
+                    (None, None) => unreachable!(),
+                }
+            })
+            .await?;
+        self.non_empty(self.ip_socket_addrs(ips))
+    }
+
+    /// Perform DNS resolution, returning a handle to cancel it along with the lookup future
+    ///
+    /// Once aborted, the future resolves to [`HickoryToSocketAddrsError::Aborted`], which is
+    /// converted to an error of kind `io::ErrorKind::Interrupted`.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let target = HickoryToSocketAddrs::new("www.rust-lang.org", 443);
+    /// let (lookup, handle) = target.lookup_cancelable();
+    /// handle.abort();
+    /// assert!(lookup.await.is_err());
+    /// # }
+    /// ```
+    pub fn lookup_cancelable(
+        &self,
+    ) -> (
+        impl Future<Output = Result<HickorySocketAddrs, HickoryToSocketAddrsError>> + '_,
+        AbortHandle,
+    ) {
+        let (fut, handle) = future::abortable(self.lookup());
+        let fut = async move { fut.await.map_err(|_| HickoryToSocketAddrsError::Aborted)? };
+        (fut, handle)
+    }
+
+    /// Perform DNS resolution lazily, yielding the resolved addresses as an async stream
+    ///
+    /// The lookup is only performed once the stream is first polled. If it fails, the stream ends
+    /// without yielding anything; use [`HickoryToSocketAddrs::lookup`] to get the error.
+    ///
+    /// ```rust
+    /// use futures_util::StreamExt;
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let target = HickoryToSocketAddrs::new("www.rust-lang.org", 443);
+    /// let mut addrs = target.stream();
+    /// while let Some(addr) = addrs.next().await {
+    ///     println!("{addr}");
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn stream(&self) -> impl futures_util::Stream<Item = SocketAddr> + Unpin + '_ {
+        use futures_util::{StreamExt, stream};
+
+        stream::once(Box::pin(self.lookup()))
+            .flat_map(|res| stream::iter(res.into_iter().flatten()))
+    }
+
+    async fn lookup_addrs(&self) -> Result<(HickorySocketAddrs, bool), HickoryToSocketAddrsError> {
+        self.check_port()?;
+        let (addrs, from_cache) = if self.srv {
+            (self.lookup_srv().await?, false)
+        } else if let Some(addrs) = self.ip_literal() {
+            (addrs, false)
+        } else {
+            match self.lookup_host().await {
+                Err(HickoryToSocketAddrsError::Resolve(err))
+                    if self.std_fallback && !err.is_no_records_found() && !is_timeout(&err) =>
+                {
+                    (self.lookup_std(err).await?, false)
+                }
+                res => res?,
+            }
+        };
+        Ok((self.non_empty(addrs)?, from_cache))
+    }
+
+    async fn lookup_std(
+        &self,
+        err: ResolveError,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %err, "hickory failed, falling back to the system resolver");
+        let host = self.host_string();
+        let port = self.port;
+        match tokio::task::spawn_blocking(move || {
+            (host.as_str(), port)
+                .to_socket_addrs()
+                .map(Iterator::collect::<Vec<_>>)
+        })
+        .await
+        {
+            Ok(Ok(addrs)) => Ok(self.socket_addrs(addrs)),
+            _ => Err(err.into()),
+        }
+    }
+
+    async fn lookup_host(&self) -> Result<(HickorySocketAddrs, bool), HickoryToSocketAddrsError> {
+        #[cfg(feature = "mdns")]
+        if self.host.to_ip().is_none() {
+            let name = self.name()?;
+            if mdns::is_local(&name) {
+                let ips = self.timed(mdns::lookup(&name)).await?;
+                return Ok((self.ip_socket_addrs(ips), false));
+            }
+        }
+        #[cfg(feature = "cache")]
+        if self.cached {
+            let (ips, from_cache) = self.lookup_cached().await?;
+            return Ok((self.ip_socket_addrs(ips), from_cache));
+        }
+        Ok((self.ip_socket_addrs(self.lookup_ip().await?), false))
+    }
+
+    #[cfg(feature = "cache")]
+    async fn lookup_cached(&self) -> Result<(Vec<IpAddr>, bool), HickoryToSocketAddrsError> {
+        let key = self.cache_key()?;
+        if let Some(ips) = cache::get(&key) {
+            return Ok((ips, true));
+        }
+        let lookup = self.lookup_ip().await?;
+        let ips = lookup.iter().collect::<Vec<_>>();
+        let valid_until = match self.cache_ttl_floor {
+            Some(floor) => lookup.valid_until().max(Instant::now() + floor),
+            None => lookup.valid_until(),
+        };
+        cache::insert(key, ips.clone(), valid_until);
+        Ok((ips, false))
+    }
+
+    /// The effective name to query, along with a hash of the configuration of the resolvers, so
+    /// that targets resolving differently don't share their entries.
+    #[cfg(feature = "cache")]
+    fn cache_key(&self) -> Result<cache::Key, HickoryToSocketAddrsError> {
+        use std::hash::DefaultHasher;
+
+        let mut hasher = DefaultHasher::new();
+        let fallbacks = self.fallbacks.iter().map(AsRef::as_ref);
+        for resolver in std::iter::once(self.resolver()?).chain(fallbacks) {
+            resolver.config_hash().hash(&mut hasher);
+        }
+        Ok((self.name()?.to_string(), hasher.finish()))
+    }
+
+    /// Perform DNS resolution and return the first resolved address
+    ///
+    /// A [`HickoryToSocketAddrsError::NoAddresses`] error is returned if there is none.
+    pub async fn resolve_first_async(&self) -> Result<SocketAddr, HickoryToSocketAddrsError> {
+        self.lookup()
+            .await?
+            .next()
+            .ok_or_else(|| HickoryToSocketAddrsError::NoAddresses(self.host_string()))
+    }
+
+    /// Perform DNS resolution and return the first resolved address, blocking the current thread
+    ///
+    /// An error of kind `io::ErrorKind::NotFound` is returned if there is none.
+    pub fn resolve_first(&self) -> io::Result<SocketAddr> {
+        self.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| HickoryToSocketAddrsError::NoAddresses(self.host_string()).into())
+    }
+
+    /// Perform DNS resolution, then try to connect to each of the resolved addresses in order,
+    /// returning the first established connection.
+    ///
+    /// The lookup options, such as [`HickoryToSocketAddrs::max_results`] or
+    /// [`HickoryToSocketAddrs::prefer_family`], are honored. If every attempt fails, the last
+    /// error is returned.
+    pub async fn connect_tcp(&self) -> io::Result<TcpStream> {
+        let mut error = None;
+        for addr in self.lookup().await? {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => error = Some(err),
+            }
+        }
+        Err(error
+            .unwrap_or_else(|| HickoryToSocketAddrsError::NoAddresses(self.host_string()).into()))
+    }
+
+    /// Perform DNS resolution and collect all the resolved addresses
+    pub async fn resolve_all_async(&self) -> Result<Vec<SocketAddr>, HickoryToSocketAddrsError> {
+        Ok(self.lookup().await?.collect())
+    }
+
+    /// Perform DNS resolution and collect all the resolved addresses, blocking the current thread
+    pub fn resolve_all(&self) -> io::Result<Vec<SocketAddr>> {
+        Ok(self.to_socket_addrs()?.collect())
+    }
+
+    /// Perform DNS resolution and return the resolved IP addresses, without attaching any port
+    pub async fn lookup_ips(
+        &self,
+    ) -> Result<impl Iterator<Item = IpAddr>, HickoryToSocketAddrsError> {
+        let ips = self
+            .instrumented(
+                async {
+                    let lookup = self.lookup_ip().await?;
+                    Ok(lookup
+                        .into_iter()
+                        .filter(|ip| self.family.matches(ip))
+                        .collect::<Vec<_>>())
+                },
+                |ips| (ips.len(), false),
+            )
+            .await?;
+        Ok(ips.into_iter())
+    }
+
+    /// Perform DNS resolution and return the resolved addresses along with the canonical name of
+    /// the host, which is the target of its CNAME records if it is an alias, or the queried name
+    /// otherwise.
+    ///
+    /// No canonical name is returned if the host is an IP address.
+    pub async fn lookup_with_canonical(
+        &self,
+    ) -> Result<(HickorySocketAddrs, Option<Name>), HickoryToSocketAddrsError> {
+        if let Some(addrs) = self.ip_literal() {
+            return Ok((self.non_empty(addrs)?, None));
+        }
+        let fut = async {
+            let lookup = self.lookup_ip().await?;
+            let canonical = lookup
+                .as_lookup()
+                .records()
+                .iter()
+                .find(|record| matches!(record.data(), RData::A(_) | RData::AAAA(_)))
+                .map_or_else(|| lookup.query().name(), |record| record.name())
+                .clone();
+            let addrs = self.non_empty(self.ip_socket_addrs(lookup))?;
+            Ok((addrs, Some(canonical)))
+        };
+        self.instrumented(fut, |(addrs, _)| (addrs.inner.len(), false))
+            .await
+    }
+
+    /// Perform DNS resolution and return hickory's raw `LookupIp` response, giving access to the
+    /// records, their TTL and the query.
+    ///
+    /// The lookup options of this `HickoryToSocketAddrs`, such as the address family, aren't
+    /// applied. As the returned type comes from hickory, relying on it couples the caller to the
+    /// version of hickory used by this crate.
+    pub async fn lookup_raw(&self) -> Result<LookupIp, HickoryToSocketAddrsError> {
+        self.instrumented(self.lookup_ip(), |lookup| (lookup.iter().count(), false))
+            .await
+    }
+
+    /// Perform DNS resolution and return the resolved addresses along with their time to live
+    ///
+    /// The TTL reflects hickory's view at query time, which accounts for the time the records
+    /// may have already spent in its cache.
+    pub async fn lookup_with_ttl(
+        &self,
+    ) -> Result<Vec<(SocketAddr, Duration)>, HickoryToSocketAddrsError> {
+        let fut = async {
+            let ips = self.lookup_ip().await?;
+            let ttl = ips.valid_until().saturating_duration_since(Instant::now());
+            Ok(ips
+                .iter()
+                .filter(|ip| self.family.matches(ip))
+                .map(|ip| (SocketAddr::new(ip, self.port), ttl))
+                .collect::<Vec<_>>())
+        };
+        self.instrumented(fut, |addrs| (addrs.len(), false)).await
+    }
+
+    /// Perform DNS resolution and return an iterator over the resolved addresses, each along with
+    /// the time to live of its own record.
+    ///
+    /// Unlike [`HickoryToSocketAddrs::lookup_with_ttl`], which reports the TTL of the response as
+    /// a whole, this allows expiring each address independently. IP address hosts are reported
+    /// with the TTL hickory assigns them.
+    pub async fn lookup_detailed_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = DetailedAddr>, HickoryToSocketAddrsError> {
+        let fut = async {
+            let lookup = self.lookup_ip().await?;
+            Ok(lookup
+                .as_lookup()
+                .records()
+                .iter()
+                .filter_map(|record| {
+                    let ip = match record.data() {
+                        RData::A(a) => IpAddr::V4(a.0),
+                        RData::AAAA(aaaa) => IpAddr::V6(aaaa.0),
+                        _ => return None,
+                    };
+                    Some(DetailedAddr {
+                        addr: SocketAddr::new(ip, self.port),
+                        ttl: Duration::from_secs(record.ttl().into()),
+                    })
+                })
+                .filter(|detailed| self.family.matches(&detailed.addr.ip()))
+                .collect::<Vec<_>>())
+        };
+        let addrs = self.instrumented(fut, |addrs| (addrs.len(), false)).await?;
+        Ok(addrs.into_iter())
+    }
+
+    /// Perform DNS resolution and return the resolved addresses along with the name server which
+    /// answered, for diagnostic purposes.
+    ///
+    /// hickory doesn't report which name server produced an answer, so the configured name servers
+    /// are queried one at a time, in order, until one of them answers. This is thus slower than
+    /// [`HickoryToSocketAddrs::lookup`] and bypasses the resolver's cache. No name server is
+    /// reported if the host is an IP address, nor if the resolver was built with another connection
+    /// provider than tokio's.
+    pub async fn lookup_with_source(
+        &self,
+    ) -> Result<(HickorySocketAddrs, Option<SocketAddr>), HickoryToSocketAddrsError> {
+        if let Some(addrs) = self.ip_literal() {
+            return Ok((self.non_empty(addrs)?, None));
+        }
+        let name = self.name()?;
+        let Some(resolver) = self.resolver()?.as_tokio() else {
+            return Ok((self.lookup().await?, None));
+        };
+        let config = resolver.config();
+        let mut error = None;
+        for nameserver in config.name_servers().iter() {
+            let single = build_resolver(
+                Resolver::builder_with_config(
+                    ResolverConfig::from_parts(
+                        config.domain().cloned(),
+                        config.search().to_vec(),
+                        NameServerConfigGroup::from(vec![nameserver.clone()]),
+                    ),
+                    TokioConnectionProvider::default(),
+                )
+                .with_options(resolver.options().clone()),
+            );
+            match self
+                .timed(async { Ok(single.lookup_ip(name.clone()).await?) })
+                .await
+            {
+                Ok(ips) => {
+                    let addrs = self.non_empty(self.ip_socket_addrs(ips))?;
+                    return Ok((addrs, Some(nameserver.socket_addr)));
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        Err(error.unwrap_or(HickoryToSocketAddrsError::NoNameServers))
+    }
+
+    /// Perform a DNS SRV lookup on the host and resolve the targets, using the ports provided by
+    /// the SRV records.
+    ///
+    /// Targets are ordered by ascending priority, then by descending weight, as described by
+    /// RFC 2782. Targets failing to resolve are skipped, unless none of them resolves.
+    pub async fn lookup_srv(&self) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        self.lookup_srv_with(|mut records| {
+            records.sort_by_key(|srv| (srv.priority(), Reverse(srv.weight())));
+            records
+        })
+        .await
+    }
+
+    /// Perform a DNS SRV lookup on the host and resolve the targets, ordering them as an RFC 2782
+    /// client should try them.
+    ///
+    /// Targets are grouped by ascending priority, and ordered within each group through a weighted
+    /// random selection, so that targets with a higher weight are more likely to come first.
+    /// Targets with a weight of 0 come last within their group. The selection can be made
+    /// deterministic through [`HickoryToSocketAddrs::srv_seed`].
+    pub async fn lookup_srv_ordered(
+        &self,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        self.lookup_srv_with(|records| match self.srv_seed {
+            Some(seed) => weighted_order(records, &mut StdRng::seed_from_u64(seed)),
+            None => weighted_order(records, &mut rand::rng()),
+        })
+        .await
+    }
+
+    async fn lookup_srv_with(
+        &self,
+        order: impl FnOnce(Vec<SRV>) -> Vec<SRV>,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        let name = self.name()?;
+        let resolver = self.resolver()?;
+        let addrs = self
+            .timed(async {
+                let records = resolver
+                    .lookup(name.clone(), RecordType::SRV)
+                    .await?
+                    .iter()
+                    .filter_map(RData::as_srv)
+                    .filter(|srv| !srv.target().is_root())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mut addrs = Vec::new();
+                let mut error = None;
+                for srv in order(records) {
+                    match resolver.lookup_ip(srv.target().clone()).await {
+                        Ok(ips) => {
+                            addrs.extend(ips.into_iter().map(|ip| SocketAddr::new(ip, srv.port())))
+                        }
+                        Err(err) => error = Some(err),
+                    }
+                }
+                match error {
+                    Some(err) if addrs.is_empty() => Err(err.into()),
+                    _ => Ok(addrs),
+                }
+            })
+            .await?;
+        Ok(self.socket_addrs(addrs))
+    }
+
+    async fn lookup_ip(&self) -> Result<LookupIp, HickoryToSocketAddrsError> {
+        let name = match self.host.to_ip() {
+            Some(ip) => Err(ip),
+            None => Ok(self.name()?),
+        };
+        let resolver = self.resolver()?;
+        self.timed(async {
+            let name = match name {
+                Ok(name) => name,
+                Err(ip) => {
+                    return resolver
+                        .lookup_ip_addr(ip)
+                        .await
+                        .map_err(|err| resolve_error(resolver, err));
+                }
+            };
+            let (res, last) = self
+                .query_with_fallbacks(resolver, |resolver| resolver.lookup_ip(name.clone()))
+                .await;
+            #[cfg(feature = "tracing")]
+            if let (true, Ok(lookup)) = (self.partial_results_warn, &res) {
+                warn_partial(last, lookup).await;
+            }
+            res.map_err(|err| resolve_error(last, err))
+        })
+        .await
+        .map(without_ipv6)
+    }
+
+    /// Perform the query through the resolver, then through each fallback while it fails for
+    /// another reason than a negative answer, returning the outcome along with the last resolver.
+    async fn query_with_fallbacks<'a, T>(
+        &'a self,
+        resolver: &'a dyn AnyResolver,
+        query: impl for<'r> Fn(&'r dyn AnyResolver) -> BoxFuture<'r, Result<T, ResolveError>>,
+    ) -> (Result<T, ResolveError>, &'a dyn AnyResolver) {
+        let mut res = query(resolver).await;
+        let mut last = resolver;
+        for fallback in &self.fallbacks {
+            match &res {
+                Err(err) if !err.is_no_records_found() => {}
+                _ => break,
+            }
+            res = query(fallback.as_ref()).await;
+            last = fallback.as_ref();
+        }
+        (res, last)
+    }
+
+    fn name(&self) -> Result<Name, HickoryToSocketAddrsError> {
+        let mut name = self
+            .host
+            .clone()
+            .into_name()
+            .map_err(|err| HickoryToSocketAddrsError::InvalidName(err.to_string()))?;
+        if name.num_labels() == 0 && !name.is_fqdn() {
+            return Err(HickoryToSocketAddrsError::EmptyHost);
+        }
+        if name.num_labels() == 0 || name.iter().flatten().any(u8::is_ascii_control) {
+            return Err(HickoryToSocketAddrsError::InvalidName(name.to_string()));
+        }
+        if let Some(origin) = self.origin.as_ref().filter(|_| !name.is_fqdn()) {
+            name = name
+                .append_domain(origin)
+                .map_err(|err| HickoryToSocketAddrsError::InvalidName(err.to_string()))?;
+            name.set_fqdn(true);
+        }
+        if self.absolute {
+            name.set_fqdn(true);
+        }
+        Ok(name)
+    }
+
+    async fn timed<T>(
+        &self,
+        fut: impl Future<Output = Result<T, HickoryToSocketAddrsError>>,
+    ) -> Result<T, HickoryToSocketAddrsError> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| HickoryToSocketAddrsError::Timeout(self.host_string()))?,
+            None => fut.await,
+        }
+    }
+
+    fn target_string(&self) -> String {
+        if self.srv {
+            self.host_string()
+        } else {
+            format!("{}:{}", self.host_string(), self.port)
+        }
+    }
+
+    fn host_string(&self) -> String {
+        match self.host.to_ip() {
+            Some(ip) => ip.to_string(),
+            None => self
+                .host
+                .clone()
+                .into_name()
+                .map_or_else(|_| String::new(), |name| name.to_string()),
+        }
+    }
+
+    fn check_port(&self) -> Result<(), HickoryToSocketAddrsError> {
+        if self.reject_zero_port && self.port == 0 && !self.srv {
+            return Err(HickoryToSocketAddrsError::InvalidPort);
+        }
+        Ok(())
+    }
+
+    fn non_empty(
+        &self,
+        addrs: HickorySocketAddrs,
+    ) -> Result<HickorySocketAddrs, HickoryToSocketAddrsError> {
+        if self.error_on_empty && addrs.inner.as_slice().is_empty() {
+            return Err(HickoryToSocketAddrsError::NoAddresses(self.host_string()));
+        }
+        Ok(addrs)
+    }
+
+    fn ip_literal(&self) -> Option<HickorySocketAddrs> {
+        if self.srv {
+            return None;
+        }
+        let ip = self.host.to_ip()?;
+        Some(self.ip_socket_addrs([ip]))
+    }
+
+    fn ip_socket_addrs(&self, ips: impl IntoIterator<Item = IpAddr>) -> HickorySocketAddrs {
+        self.socket_addrs(
+            ips.into_iter()
+                .map(|ip| SocketAddr::new(ip, self.port))
+                .collect(),
+        )
+    }
+
+    fn socket_addrs(&self, mut addrs: Vec<SocketAddr>) -> HickorySocketAddrs {
+        addrs.retain(|addr| self.family.matches(&addr.ip()));
+        if let Some(scope_id) = self.scope_id {
+            for addr in &mut addrs {
+                if let SocketAddr::V6(addr) = addr {
+                    if addr.ip().is_unicast_link_local() {
+                        addr.set_scope_id(scope_id);
+                    }
+                }
+            }
+        }
+        if self.dedup {
+            let mut seen = HashSet::new();
+            addrs.retain(|addr| seen.insert(*addr));
+        }
+        if self.shuffle {
+            match self.shuffle_seed {
+                Some(seed) => addrs.shuffle(&mut StdRng::seed_from_u64(seed)),
+                None => addrs.shuffle(&mut rand::rng()),
+            }
+        }
+        if self.rfc6724 {
+            rfc6724::sort(&mut addrs);
+        }
+        if self.preferred_family != AddressFamily::Any {
+            addrs.sort_by_key(|addr| !self.preferred_family.matches(&addr.ip()));
+        }
+        if self.happy_eyeballs {
+            addrs = interleave(addrs);
+        }
+        if let Some(counter) = self.round_robin.as_ref().filter(|_| !addrs.is_empty()) {
+            let offset = counter.fetch_add(1, atomic::Ordering::Relaxed);
+            let len = addrs.len();
+            addrs.rotate_left(offset % len);
+        }
+        if let Some(max_results) = self.max_results {
+            addrs.truncate(max_results);
+        }
+        HickorySocketAddrs {
+            inner: addrs.into_iter(),
+        }
+    }
+
+    /// An owned copy of this target, without the completion callback, to perform the lookup on
+    /// the background runtime.
+    fn detached(&self) -> Result<HickoryToSocketAddrs<Name>, HickoryToSocketAddrsError> {
+        let host = self
+            .host
+            .clone()
+            .into_name()
+            .map_err(|err| HickoryToSocketAddrsError::InvalidName(err.to_string()))?;
+        Ok(HickoryToSocketAddrs {
+            host,
+            port: self.port,
+            resolver: self.resolver.clone(),
+            fallbacks: self.fallbacks.clone(),
+            timeout: self.timeout,
+            family: self.family,
+            preferred_family: self.preferred_family,
+            scope_id: self.scope_id,
+            rfc6724: self.rfc6724,
+            round_robin: self.round_robin.clone(),
+            happy_eyeballs: self.happy_eyeballs,
+            dedup: self.dedup,
+            max_results: self.max_results,
+            shuffle: self.shuffle,
+            shuffle_seed: self.shuffle_seed,
+            srv_seed: self.srv_seed,
+            error_on_empty: self.error_on_empty,
+            srv: self.srv,
+            std_fallback: self.std_fallback,
+            reject_zero_port: self.reject_zero_port,
+            absolute: self.absolute,
+            origin: self.origin.clone(),
+            on_complete: None,
+            #[cfg(feature = "cache")]
+            cached: self.cached,
+            #[cfg(feature = "cache")]
+            cache_ttl_floor: self.cache_ttl_floor,
+            #[cfg(feature = "tracing")]
+            partial_results_warn: self.partial_results_warn,
+        })
+    }
+
+    fn with_fresh_resolvers(&self) -> Result<Self, ResolveError> {
+        let resolver = match (self.resolver.clone(), self.resolver()?.as_tokio()) {
+            (_, Some(resolver)) => erase(rebuild_resolver(resolver)),
+            (Some(resolver), None) => resolver,
+            (None, None) => unreachable!("the default resolver is a tokio one"),
+        };
+        Ok(Self {
+            resolver: Some(resolver),
+            fallbacks: self
+                .fallbacks
+                .iter()
+                .map(|fallback| match fallback.as_tokio() {
+                    Some(fallback) => erase(rebuild_resolver(fallback)),
+                    None => fallback.clone(),
+                })
+                .collect(),
+            ..self.clone()
+        })
+    }
+
+    fn resolver(&self) -> Result<&dyn AnyResolver, ResolveError> {
+        match self.resolver.as_deref() {
+            Some(resolver) => Ok(resolver),
+            None => Ok(default_erased_resolver()?),
+        }
+    }
+}
+
+impl<T: IntoName + Clone + PartialEq> PartialEq for HickoryToSocketAddrs<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.port == other.port && self.srv == other.srv
+    }
+}
+
+impl<T: IntoName + Clone + Eq> Eq for HickoryToSocketAddrs<T> {}
+
+impl<T: IntoName + Clone + Ord> PartialOrd for HickoryToSocketAddrs<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: IntoName + Clone + Ord> Ord for HickoryToSocketAddrs<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.host, self.port, self.srv).cmp(&(&other.host, other.port, other.srv))
+    }
+}
+
+impl<T: IntoName + Clone + Hash> Hash for HickoryToSocketAddrs<T> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.host.hash(state);
+        self.port.hash(state);
+        self.srv.hash(state);
+    }
+}
+
+impl<T: IntoName + Clone + fmt::Display> fmt::Display for HickoryToSocketAddrs<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(IpAddr::V6(ip)) = self.host.to_ip() {
+            match self.scope_id {
+                Some(scope_id) if ip.is_unicast_link_local() => {
+                    write!(fmt, "[{}%{scope_id}]:{}", self.host, self.port)
+                }
+                _ => write!(fmt, "[{}]:{}", self.host, self.port),
+            }
+        } else {
+            write!(fmt, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Builder for a [`HickoryToSocketAddrs`] using a custom resolver configuration
+///
+/// When nothing is overridden, the resulting `HickoryToSocketAddrs` uses the process-wide default
+/// resolver, built from the system configuration.
+#[derive(Debug)]
+pub struct HickoryToSocketAddrsBuilder<T: IntoName + Clone> {
+    host: T,
+    port: u16,
+    config: Option<ResolverConfig>,
+    options: Option<ResolverOpts>,
+    overrides: OptionsOverrides,
+    nameservers: Option<Vec<SocketAddr>>,
+    bind_addr: Option<IpAddr>,
+    protocol: Option<Protocol>,
+    search_domains: Option<Vec<Name>>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct OptionsOverrides {
+    ip_strategy: Option<LookupIpStrategy>,
+    attempts: Option<usize>,
+    cache_size: Option<usize>,
+    edns0: Option<bool>,
+    ndots: Option<usize>,
+    negative_cache: Option<bool>,
+    #[cfg(feature = "dnssec")]
+    validate: Option<bool>,
+}
+
+impl OptionsOverrides {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&self, options: &mut ResolverOpts) {
+        if let Some(ip_strategy) = self.ip_strategy {
+            options.ip_strategy = ip_strategy;
+        }
+        if let Some(attempts) = self.attempts {
+            options.attempts = attempts;
+        }
+        if let Some(cache_size) = self.cache_size {
+            options.cache_size = cache_size as _;
+        }
+        if let Some(edns0) = self.edns0 {
+            options.edns0 = edns0;
+        }
+        if let Some(ndots) = self.ndots {
+            options.ndots = ndots;
+        }
+        if self.negative_cache == Some(false) {
+            options.negative_min_ttl = None;
+            options.negative_max_ttl = Some(Duration::ZERO);
+        }
+        #[cfg(feature = "dnssec")]
+        if let Some(validate) = self.validate {
+            options.validate = validate;
+        }
+    }
+}
+
+impl<H: IntoName + Clone> HickoryToSocketAddrsBuilder<H> {
+    /// Use the given `ResolverConfig` instead of the system one.
+    pub fn config(mut self, config: ResolverConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use the given `ResolverOpts` instead of the system ones.
+    pub fn options(mut self, options: ResolverOpts) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Query the given name servers, over both UDP and TCP, instead of the configured ones.
+    ///
+    /// The search domains of the configuration, if any, are kept.
+    pub fn nameservers(mut self, nameservers: &[SocketAddr]) -> Self {
+        self.nameservers = Some(nameservers.to_vec());
+        self
+    }
+
+    /// Control which queries (A, AAAA or both) are sent to the name servers.
+    ///
+    /// Unlike [`HickoryToSocketAddrs::address_family`], this affects the queries actually issued.
+    pub fn lookup_strategy(mut self, strategy: LookupIpStrategy) -> Self {
+        self.overrides.ip_strategy = Some(strategy);
+        self
+    }
+
+    /// Set how many times a query is attempted before giving up, to cope with transient packet
+    /// loss.
+    ///
+    /// Each attempt is bounded by the resolver's own per-query timeout, while
+    /// [`HickoryToSocketAddrs::with_timeout`] bounds the whole resolution, including all the
+    /// attempts. The default is hickory's one.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.overrides.attempts = Some(attempts);
+        self
+    }
+
+    /// Set the maximum number of answers kept in the resolver's cache, instead of hickory's
+    /// default.
+    ///
+    /// A larger cache helps when resolving many distinct hosts, while a size of 0 effectively
+    /// disables caching, e.g. to isolate tests.
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.overrides.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Advertise EDNS0 support in the queries, allowing UDP answers larger than 512 bytes instead
+    /// of relying on truncation and retrying over TCP.
+    ///
+    /// hickory advertises a fixed UDP payload size (1232 bytes, as recommended by the DNS flag day
+    /// 2020), which cannot be customized. EDNS0 is disabled by default.
+    pub fn edns0(mut self, edns0: bool) -> Self {
+        self.overrides.edns0 = Some(edns0);
+        self
+    }
+
+    /// Set the number of dots a relative name must contain to be queried as is before trying the
+    /// search domains, instead of the one from the system configuration.
+    pub fn ndots(mut self, ndots: u8) -> Self {
+        self.overrides.ndots = Some(ndots.into());
+        self
+    }
+
+    /// Replace the search domains, along with the local domain, of the configuration.
+    ///
+    /// With an empty list and [`HickoryToSocketAddrsBuilder::ndots`] set to 0, names are always
+    /// queried verbatim, making the resolution independent of the environment.
+    pub fn search_domains(mut self, search_domains: Vec<Name>) -> Self {
+        self.search_domains = Some(search_domains);
+        self
+    }
+
+    /// Cache negative answers, such as `NXDOMAIN`, to avoid querying the name servers again for
+    /// names which don't exist.
+    ///
+    /// The negative answers are cached for the TTL given by the SOA record of the zone. This is
+    /// enabled by default.
+    pub fn negative_cache(mut self, negative_cache: bool) -> Self {
+        self.overrides.negative_cache = Some(negative_cache);
+        self
+    }
+
+    /// Validate the DNSSEC signatures of the answers, failing closed on bogus ones.
+    ///
+    /// The root trust anchor bundled with hickory (the IANA root KSK) is used. Validation
+    /// failures (bogus proofs) are reported through [`HickoryToSocketAddrsError::Dnssec`], distinct
+    /// from a plain negative answer; transport errors are still reported as `Resolve`.
+    #[cfg(feature = "dnssec")]
+    pub fn validate_dnssec(mut self, validate: bool) -> Self {
+        self.overrides.validate = Some(validate);
+        self
+    }
+
+    /// Only use the given transport, such as [`Protocol::Tcp`] on networks blocking UDP, to reach
+    /// the name servers.
+    ///
+    /// By default, both UDP and TCP are used, TCP being the fallback for truncated answers. This
+    /// applies to the name servers given through [`HickoryToSocketAddrsBuilder::nameservers`]
+    /// too. Building fails with [`HickoryToSocketAddrsError::NoNameServers`] if none of the name
+    /// servers uses this protocol.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Send the queries from the given local address, e.g. to egress from a specific interface.
+    ///
+    /// This only affects the resolver built for this `HickoryToSocketAddrs`, not the process-wide
+    /// default one.
+    pub fn bind_addr(mut self, bind_addr: IpAddr) -> Self {
+        self.bind_addr = Some(bind_addr);
+        self
+    }
+
+    /// Build the resolver and the `HickoryToSocketAddrs` using it.
+    pub fn build(mut self) -> Result<HickoryToSocketAddrs<H>, HickoryToSocketAddrsError> {
+        let mut config = match self.nameservers {
+            Some(nameservers) => Some(nameservers_config(self.config.as_ref(), &nameservers)?),
+            None => self.config,
+        };
+        if self.bind_addr.is_some() || self.protocol.is_some() {
+            let base = base_config(config, &mut self.options)?;
+            config = Some(nameservers_override(&base, self.bind_addr, self.protocol)?);
+        }
+        if let Some(search) = self.search_domains.take() {
+            let base = base_config(config, &mut self.options)?;
+            config = Some(ResolverConfig::from_parts(
+                None,
+                search,
+                NameServerConfigGroup::from(base.name_servers().to_vec()),
+            ));
+        }
+        if config.is_none() && self.options.is_none() && self.overrides.is_empty() {
+            return Ok(HickoryToSocketAddrs::new(self.host, self.port));
+        }
+        let mut builder = match config {
+            Some(config) => {
+                Resolver::builder_with_config(config, TokioConnectionProvider::default())
+            }
+            None => Resolver::builder_tokio()?,
+        };
+        if let Some(options) = self.options {
+            builder = builder.with_options(options);
+        }
+        self.overrides.apply(builder.options_mut());
+        Ok(HickoryToSocketAddrs::with_resolver(
+            self.host,
             self.port,
+            build_resolver(builder),
         ))
     }
 }
 
+fn nameservers_config(
+    config: Option<&ResolverConfig>,
+    nameservers: &[SocketAddr],
+) -> Result<ResolverConfig, HickoryToSocketAddrsError> {
+    if nameservers.is_empty() {
+        return Err(HickoryToSocketAddrsError::NoNameServers);
+    }
+    let group = nameservers
+        .iter()
+        .flat_map(|addr| {
+            [Protocol::Udp, Protocol::Tcp].map(|protocol| NameServerConfig::new(*addr, protocol))
+        })
+        .collect::<Vec<_>>();
+    Ok(match config {
+        Some(config) => ResolverConfig::from_parts(
+            config.domain().cloned(),
+            config.search().to_vec(),
+            NameServerConfigGroup::from(group),
+        ),
+        None => ResolverConfig::from_parts(None, Vec::new(), NameServerConfigGroup::from(group)),
+    })
+}
+
+fn base_config(
+    config: Option<ResolverConfig>,
+    options: &mut Option<ResolverOpts>,
+) -> Result<ResolverConfig, HickoryToSocketAddrsError> {
+    Ok(match config {
+        Some(config) => config,
+        None => {
+            let (config, system_options) = read_system_conf()?;
+            options.get_or_insert(system_options);
+            config
+        }
+    })
+}
+
+fn nameservers_override(
+    config: &ResolverConfig,
+    bind_addr: Option<IpAddr>,
+    protocol: Option<Protocol>,
+) -> Result<ResolverConfig, HickoryToSocketAddrsError> {
+    let group = config
+        .name_servers()
+        .iter()
+        .filter(|nameserver| protocol.is_none_or(|protocol| nameserver.protocol == protocol))
+        .map(|nameserver| {
+            let mut nameserver = nameserver.clone();
+            if let Some(bind_addr) = bind_addr {
+                nameserver.bind_addr = Some(SocketAddr::new(bind_addr, 0));
+            }
+            nameserver
+        })
+        .collect::<Vec<_>>();
+    if group.is_empty() {
+        return Err(HickoryToSocketAddrsError::NoNameServers);
+    }
+    Ok(ResolverConfig::from_parts(
+        config.domain().cloned(),
+        config.search().to_vec(),
+        NameServerConfigGroup::from(group),
+    ))
+}
+
+/// Whether hickory gave up waiting for the name servers, in which case the system resolver is
+/// unlikely to fare any better.
+fn is_timeout(err: &ResolveError) -> bool {
+    err.proto().is_some_and(|err| match err.kind() {
+        ProtoErrorKind::Timeout => true,
+        ProtoErrorKind::Io(err) => err.kind() == io::ErrorKind::TimedOut,
+        _ => false,
+    })
+}
+
+#[cfg_attr(not(feature = "dnssec"), allow(unused_variables))]
+fn resolve_error(resolver: &dyn AnyResolver, err: ResolveError) -> HickoryToSocketAddrsError {
+    #[cfg(feature = "dnssec")]
+    if resolver.options().validate && is_bogus(&err) {
+        return HickoryToSocketAddrsError::Dnssec(err);
+    }
+    HickoryToSocketAddrsError::Resolve(err)
+}
+
+/// Whether the error comes from an answer whose DNSSEC proof didn't validate, as opposed to a
+/// timeout, a refused connection or a SERVFAIL which happen regardless of validation.
+#[cfg(feature = "dnssec")]
+fn is_bogus(err: &ResolveError) -> bool {
+    err.proto().is_some_and(
+        |err| matches!(err.kind(), ProtoErrorKind::Nsec { proof, .. } if proof.is_bogus()),
+    )
+}
+
+#[cfg(feature = "doh")]
+fn doh_config(
+    url: &str,
+    bootstrap_ips: &[IpAddr],
+) -> Result<ResolverConfig, HickoryToSocketAddrsError> {
+    if bootstrap_ips.is_empty() {
+        return Err(HickoryToSocketAddrsError::NoNameServers);
+    }
+    let rest = url
+        .strip_prefix("https://")
+        .ok_or(HickoryToSocketAddrsError::InvalidUrl)?;
+    let (authority, endpoint) = match rest.find('/') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, "/dns-query"),
+    };
+    let (server_name, port) = split_host_port(authority)?;
+    let port = port.map_or(Ok(443), parse_port)?;
+    let group = bootstrap_ips
+        .iter()
+        .map(|ip| {
+            let mut config = NameServerConfig::new(SocketAddr::new(*ip, port), Protocol::Https);
+            config.tls_dns_name = Some(server_name.to_owned());
+            config.http_endpoint = Some(endpoint.to_owned());
+            config
+        })
+        .collect::<Vec<_>>();
+    Ok(ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from(group),
+    ))
+}
+
+impl HickoryToSocketAddrs<String> {
+    /// Parse a `host:port` string, falling back to the given port if there is none.
+    ///
+    /// A present but invalid port is still rejected.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// let target = HickoryToSocketAddrs::parse_with_default_port("www.rust-lang.org", 443)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn parse_with_default_port(
+        s: &str,
+        default: u16,
+    ) -> Result<Self, HickoryToSocketAddrsError> {
+        let (host, port) = split_host_port(s)?;
+        if host.is_empty() {
+            return Err(HickoryToSocketAddrsError::EmptyHost);
+        }
+        let (host, scope_id) = split_zone(host)?;
+        let port = port.map_or(Ok(default), parse_port)?;
+        Ok(Self {
+            scope_id,
+            ..Self::new(host.to_owned(), port)
+        })
+    }
+
+    /// Parse a comma-separated list of `host:port` targets, trimming the whitespace around them.
+    ///
+    /// The first malformed entry is reported through [`HickoryToSocketAddrsError::InvalidEntry`].
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// let targets = HickoryToSocketAddrs::parse_list("a.example.com:443, [::1]:443")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, HickoryToSocketAddrsError> {
+        s.split(',')
+            .map(str::trim)
+            .map(|entry| {
+                entry.parse().map_err(|err| {
+                    HickoryToSocketAddrsError::InvalidEntry(entry.to_owned(), Box::new(err))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "url")]
+impl HickoryToSocketAddrs<String> {
+    /// Create a `HickoryToSocketAddrs` from the host and port of an URL.
+    ///
+    /// If the URL doesn't contain any port, the default one for its scheme is used (e.g. 443 for
+    /// `https`). IP literal hosts are preserved.
+    ///
+    /// ```rust
+    /// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+    ///
+    /// let url = url::Url::parse("https://www.rust-lang.org/learn").unwrap();
+    /// let target = HickoryToSocketAddrs::from_url(&url)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn from_url(url: &url::Url) -> Result<Self, HickoryToSocketAddrsError> {
+        let host = match url.host().ok_or(HickoryToSocketAddrsError::InvalidUrl)? {
+            url::Host::Domain(domain) => domain.to_owned(),
+            url::Host::Ipv4(ip) => ip.to_string(),
+            url::Host::Ipv6(ip) => ip.to_string(),
+        };
+        let port = url
+            .port_or_known_default()
+            .ok_or(HickoryToSocketAddrsError::MissingPort)?;
+        Ok(Self::new(host, port))
+    }
+}
+
+impl TryFrom<(&str, u16)> for HickoryToSocketAddrs<String> {
+    type Error = HickoryToSocketAddrsError;
+
+    /// Create a `HickoryToSocketAddrs` from a host and a port, validating the host.
+    fn try_from((host, port): (&str, u16)) -> Result<Self, Self::Error> {
+        if host.is_empty() {
+            return Err(HickoryToSocketAddrsError::EmptyHost);
+        }
+        Self::try_new(host.to_owned(), port)
+    }
+}
+
+impl From<(IpAddr, u16)> for HickoryToSocketAddrs<String> {
+    /// Create a `HickoryToSocketAddrs` from an IP address and a port, which resolves to itself
+    /// without performing any query.
+    fn from((ip, port): (IpAddr, u16)) -> Self {
+        Self::new(ip.to_string(), port)
+    }
+}
+
 impl FromStr for HickoryToSocketAddrs<String> {
-    type Err = io::Error;
+    type Err = HickoryToSocketAddrsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = split_host_port(s)?;
+        if host.is_empty() {
+            return Err(HickoryToSocketAddrsError::EmptyHost);
+        }
+        let (host, scope_id) = split_zone(host)?;
+        let port = parse_port(port.ok_or(HickoryToSocketAddrsError::MissingPort)?)?;
+        Ok(Self {
+            scope_id,
+            ..Self::new(host.to_owned(), port)
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HickoryToSocketAddrs<String> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for HickoryToSocketAddrs<String> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn split_host_port(s: &str) -> Result<(&str, Option<&str>), HickoryToSocketAddrsError> {
+    if let Some(rest) = s.strip_prefix('[') {
+        let (host, rest) = rest
+            .split_once(']')
+            .ok_or(HickoryToSocketAddrsError::MissingPort)?;
+        if rest.is_empty() {
+            return Ok((host, None));
+        }
+        let port = rest
+            .strip_prefix(':')
+            .ok_or(HickoryToSocketAddrsError::MissingPort)?;
+        return Ok((host, Some(port)));
+    }
+    match s.rsplit_once(':') {
+        Some((host, _)) if host.contains(':') => Err(HickoryToSocketAddrsError::MissingPort),
+        Some((host, port)) => Ok((host, Some(port))),
+        None => Ok((s, None)),
+    }
+}
+
+/// Split the `%zone` suffix of an IPv6 address, given either as an interface name or index, and
+/// resolve it to a scope ID.
+fn split_zone(host: &str) -> Result<(&str, Option<u32>), HickoryToSocketAddrsError> {
+    let Some((ip, zone)) = host.split_once('%') else {
+        return Ok((host, None));
+    };
+    if ip.parse::<Ipv6Addr>().is_err() {
+        return Ok((host, None));
+    }
+    let scope_id = match zone.parse() {
+        Ok(index) => index,
+        Err(_) => interface_index(zone)
+            .ok_or_else(|| HickoryToSocketAddrsError::InvalidZone(zone.to_owned()))?,
+    };
+    Ok((ip, Some(scope_id)))
+}
 
-    fn from_str(s: &str) -> io::Result<Self> {
-        let (host, port_str) = s
-            .rsplit_once(':')
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address"))?;
-        let port = port_str
-            .parse()
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid port value"))?;
-        Ok(Self::new(host.to_owned(), port))
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: the name is a valid NUL-terminated string.
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => None,
+        index => Some(index),
     }
 }
 
+#[cfg(not(unix))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+fn parse_port(port: &str) -> Result<u16, HickoryToSocketAddrsError> {
+    port.parse()
+        .map_err(|_| HickoryToSocketAddrsError::InvalidPort)
+}
+
 impl<T: IntoName + Clone> ToSocketAddrs for HickoryToSocketAddrs<T> {
     type Iter = HickorySocketAddrs;
 
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
-        block_on(self.lookup())
+        self.check_port()?;
+        if let Some(addrs) = self.ip_literal() {
+            return Ok(self.non_empty(addrs)?);
+        }
+        // The configured timeout, if any, is already enforced by the lookup itself.
+        let ceiling = match self.timeout {
+            Some(_) => None,
+            None => blocking_timeout(),
+        };
+        let runtime = Handle::try_current().ok();
+        if let Some(handle) = runtime.as_ref() {
+            if handle.runtime_flavor() == RuntimeFlavor::MultiThread {
+                // Expiring the ceiling is reported to the completion callback like any failure.
+                let lookup = async {
+                    match ceiling {
+                        Some(ceiling) => tokio::time::timeout(ceiling, self.lookup_addrs())
+                            .await
+                            .map_err(|_| {
+                            HickoryToSocketAddrsError::Timeout(self.host_string())
+                        })?,
+                        None => self.lookup_addrs().await,
+                    }
+                };
+                let lookup = self.instrumented(lookup, |(addrs, from_cache)| {
+                    (addrs.inner.len(), *from_cache)
+                });
+                return Ok(tokio::task::block_in_place(|| handle.block_on(lookup))?.0);
+            }
+        }
+        // Either there is no runtime, or we may be running inside of a current thread runtime,
+        // which we thus cannot block on. Perform the lookup on the background runtime instead.
+        // The connections of the resolvers may then belong to the runtime we're about to block, in
+        // which case fresh resolvers are used.
+        let start = Instant::now();
+        let mut target = self.detached()?;
+        if runtime.is_some() {
+            target = target.with_fresh_resolvers()?;
+        }
+        let res = block_on_background(async move { target.lookup().await }, ceiling)?
+            .unwrap_or_else(|| Err(HickoryToSocketAddrsError::Timeout(self.host_string())));
+        if let Some(on_complete) = &self.on_complete {
+            let outcome = res.as_ref().map(HickorySocketAddrs::len);
+            (on_complete.0)(self, outcome, start.elapsed());
+        }
+        Ok(res?)
+    }
+}
+
+/// Async counterpart of `std::net::ToSocketAddrs`, resolving through `hickory-dns` without
+/// blocking the current thread
+///
+/// ```rust
+/// use hickory_to_socket_addrs::AsyncToSocketAddrs;
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let socket_addrs = "www.rust-lang.org:443"
+///     .to_socket_addrs_async()
+///     .await?
+///     .collect::<Vec<_>>();
+/// # Ok(())
+/// # }
+/// ```
+pub trait AsyncToSocketAddrs {
+    /// Resolve to an iterator of `SocketAddr`
+    fn to_socket_addrs_async(&self) -> impl Future<Output = io::Result<HickorySocketAddrs>> + Send;
+}
+
+impl<T: IntoName + Clone + Send + Sync> AsyncToSocketAddrs for HickoryToSocketAddrs<T> {
+    async fn to_socket_addrs_async(&self) -> io::Result<HickorySocketAddrs> {
+        Ok(self.lookup().await?)
+    }
+}
+
+/// Parse a `host:port` string
+impl AsyncToSocketAddrs for str {
+    async fn to_socket_addrs_async(&self) -> io::Result<HickorySocketAddrs> {
+        Ok(self.parse::<HickoryToSocketAddrs<_>>()?.lookup().await?)
+    }
+}
+
+impl AsyncToSocketAddrs for (&str, u16) {
+    fn to_socket_addrs_async(&self) -> impl Future<Output = io::Result<HickorySocketAddrs>> + Send {
+        let target = HickoryToSocketAddrs::new(self.0, self.1);
+        async move { Ok(target.lookup().await?) }
+    }
+}
+
+/// Perform the lookup, consuming the `HickoryToSocketAddrs`
+///
+/// ```rust
+/// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let socket_addrs = HickoryToSocketAddrs::new("www.rust-lang.org", 443)
+///     .await?
+///     .collect::<Vec<_>>();
+/// # Ok(())
+/// # }
+/// ```
+impl<T: IntoName + Clone + Send + Sync + 'static> IntoFuture for HickoryToSocketAddrs<T> {
+    type Output = io::Result<HickorySocketAddrs>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move { Ok(self.lookup().await?) })
+    }
+}
+
+/// Outcome of [`HickoryToSocketAddrs::lookup_detailed`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct LookupDetails {
+    /// The resolved addresses
+    pub addrs: HickorySocketAddrs,
+    /// Whether the addresses were served from the cache enabled through
+    /// `HickoryToSocketAddrs::cached`
+    pub from_cache: bool,
+    /// How long the lookup took
+    pub elapsed: Duration,
+}
+
+/// Item of [`HickoryToSocketAddrs::lookup_detailed_iter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DetailedAddr {
+    /// The resolved address
+    pub addr: SocketAddr,
+    /// The time to live of the record the address comes from
+    pub ttl: Duration,
+}
+
+/// Outcome of [`HickoryToSocketAddrs::lookup_grouped`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GroupedAddrs {
+    /// The resolved IPv4 addresses
+    pub v4: Vec<SocketAddrV4>,
+    /// The resolved IPv6 addresses
+    pub v6: Vec<SocketAddrV6>,
+}
+
+/// Address family of the resolved addresses to yield
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    /// Only yield IPv4 addresses
+    V4Only,
+    /// Only yield IPv6 addresses
+    V6Only,
+    /// Yield both IPv4 and IPv6 addresses
+    #[default]
+    Any,
+}
+
+impl AddressFamily {
+    fn matches(self, ip: &IpAddr) -> bool {
+        if cfg!(feature = "no-ipv6") && ip.is_ipv6() {
+            return false;
+        }
+        match self {
+            Self::V4Only => ip.is_ipv4(),
+            Self::V6Only => ip.is_ipv6(),
+            Self::Any => true,
+        }
     }
 }
 
 /// Iterator for SocketAddr resolved by `hickory-dns`
-pub struct HickorySocketAddrs(LookupIpIntoIter, u16);
+///
+/// The resolved addresses are held in memory, so cloning it allows walking them again, in the
+/// same order, without performing a new query. A clone only yields the addresses the original
+/// iterator hasn't yielded yet.
+#[derive(Clone)]
+pub struct HickorySocketAddrs {
+    inner: vec::IntoIter<SocketAddr>,
+}
+
+impl HickorySocketAddrs {
+    /// Replace the port of all the remaining addresses.
+    pub fn with_port(self, port: u16) -> Self {
+        self.map_port(|_| port)
+    }
+
+    /// Replace the port of each remaining address with the one returned by the given function.
+    ///
+    /// This is useful for protocols where the port to use differs from the one the lookup was
+    /// performed with.
+    pub fn map_port(self, mut f: impl FnMut(SocketAddr) -> u16) -> Self {
+        Self {
+            inner: self
+                .inner
+                .map(|mut addr| {
+                    addr.set_port(f(addr));
+                    addr
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }
+    }
+}
 
 impl Iterator for HickorySocketAddrs {
     type Item = SocketAddr;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(SocketAddr::new(self.0.next()?, self.1))
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
     }
 }
 
+/// The remaining number of addresses is always known, allowing to log it or to allocate
+/// accordingly before consuming the iterator.
+impl ExactSizeIterator for HickorySocketAddrs {}
+
 impl fmt::Debug for HickorySocketAddrs {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_tuple("HickorySocketAddrs").finish()
     }
 }
 
-fn block_on<T>(fut: impl Future<Output = io::Result<T>>) -> io::Result<T> {
-    if let Ok(handle) = tokio::runtime::Handle::try_current() {
-        handle.block_on(fut)
-    } else {
-        tokio::runtime::Builder::new_current_thread()
+/// Error returned when parsing or resolving a [`HickoryToSocketAddrs`]
+///
+/// It can be converted into an `io::Error` to satisfy the `ToSocketAddrs` signature.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HickoryToSocketAddrsError {
+    /// The address to parse isn't of the `host:port` form
+    MissingPort,
+    /// The address to parse contains an invalid port
+    InvalidPort,
+    /// The DNS resolution failed
+    Resolve(ResolveError),
+    /// The DNS resolution of the given `host:port` target failed
+    ResolveTarget(String, ResolveError),
+    /// The DNS resolution of the given host timed out
+    Timeout(String),
+    /// An empty list of name servers was provided
+    NoNameServers,
+    /// The provided URL is invalid
+    InvalidUrl,
+    /// The host is empty
+    EmptyHost,
+    /// The host isn't a valid DNS name
+    InvalidName(String),
+    /// The zone of the IPv6 address is neither a known interface nor an index
+    InvalidZone(String),
+    /// The global setting was already initialized
+    AlreadyInitialized,
+    /// The given host didn't resolve to any address
+    NoAddresses(String),
+    /// The given entry of a list of targets is malformed
+    InvalidEntry(String, Box<HickoryToSocketAddrsError>),
+    /// The given `resolv.conf` file couldn't be read or parsed
+    ResolvConf(PathBuf, io::Error),
+    /// The DNS resolution was aborted through its `AbortHandle`
+    Aborted,
+    /// The multicast DNS query couldn't be sent
+    #[cfg(feature = "mdns")]
+    Mdns(String),
+    /// The answer failed DNSSEC validation (its proof is bogus)
+    #[cfg(feature = "dnssec")]
+    Dnssec(ResolveError),
+}
+
+impl fmt::Display for HickoryToSocketAddrsError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPort => fmt.write_str("invalid socket address"),
+            Self::InvalidPort => fmt.write_str("invalid port value"),
+            Self::Resolve(err) => write!(fmt, "{err}"),
+            Self::ResolveTarget(target, err) => write!(fmt, "failed to resolve {target}: {err}"),
+            Self::Timeout(host) => write!(fmt, "timed out while resolving {host}"),
+            Self::NoNameServers => fmt.write_str("no name server provided"),
+            Self::InvalidUrl => fmt.write_str("invalid URL"),
+            Self::EmptyHost => fmt.write_str("empty host"),
+            Self::InvalidName(name) => write!(fmt, "invalid DNS name: {name}"),
+            Self::InvalidZone(zone) => write!(fmt, "invalid IPv6 zone: {zone}"),
+            Self::AlreadyInitialized => fmt.write_str("already initialized"),
+            Self::NoAddresses(host) => write!(fmt, "no address found for {host}"),
+            Self::InvalidEntry(entry, err) => write!(fmt, "invalid entry {entry:?}: {err}"),
+            Self::ResolvConf(path, err) => write!(fmt, "failed to load {}: {err}", path.display()),
+            Self::Aborted => fmt.write_str("DNS resolution aborted"),
+            #[cfg(feature = "mdns")]
+            Self::Mdns(err) => write!(fmt, "multicast DNS query failed: {err}"),
+            #[cfg(feature = "dnssec")]
+            Self::Dnssec(err) => write!(fmt, "DNSSEC validation failed: {err}"),
+        }
+    }
+}
+
+impl error::Error for HickoryToSocketAddrsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Resolve(err) | Self::ResolveTarget(_, err) => Some(err),
+            #[cfg(feature = "dnssec")]
+            Self::Dnssec(err) => Some(err),
+            Self::InvalidEntry(_, err) => Some(err),
+            Self::ResolvConf(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ResolveError> for HickoryToSocketAddrsError {
+    fn from(err: ResolveError) -> Self {
+        Self::Resolve(err)
+    }
+}
+
+impl From<ProtoError> for HickoryToSocketAddrsError {
+    fn from(err: ProtoError) -> Self {
+        Self::Resolve(err.into())
+    }
+}
+
+impl HickoryToSocketAddrsError {
+    /// The query which got a negative answer, if this is a resolution failure caused by the lack of
+    /// matching records.
+    ///
+    /// This gives the effective name which was queried, after the search domains were appended,
+    /// along with the record type (e.g. `A` or `AAAA`), for diagnostic purposes.
+    pub fn query(&self) -> Option<&Query> {
+        let err = match self {
+            Self::Resolve(err) | Self::ResolveTarget(_, err) => err,
+            #[cfg(feature = "dnssec")]
+            Self::Dnssec(err) => err,
+            _ => return None,
+        };
+        match err.proto()?.kind() {
+            ProtoErrorKind::NoRecordsFound { query, .. } => Some(query),
+            _ => None,
+        }
+    }
+
+    /// The `io::ErrorKind` this error is converted to
+    ///
+    /// Negative answers, such as `NXDOMAIN`, are mapped to `io::ErrorKind::NotFound`, and name
+    /// servers failing to answer in time to `io::ErrorKind::TimedOut`, telling them apart from
+    /// other resolution failures.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::MissingPort
+            | Self::InvalidPort
+            | Self::NoNameServers
+            | Self::InvalidUrl
+            | Self::EmptyHost
+            | Self::InvalidName(_)
+            | Self::InvalidZone(_)
+            | Self::InvalidEntry(..) => io::ErrorKind::InvalidInput,
+            Self::Timeout(_) => io::ErrorKind::TimedOut,
+            Self::AlreadyInitialized => io::ErrorKind::AlreadyExists,
+            Self::NoAddresses(_) => io::ErrorKind::NotFound,
+            Self::ResolvConf(_, err) => err.kind(),
+            Self::Aborted => io::ErrorKind::Interrupted,
+            #[cfg(feature = "mdns")]
+            Self::Mdns(_) => io::ErrorKind::Other,
+            Self::Resolve(err) | Self::ResolveTarget(_, err) if err.is_no_records_found() => {
+                io::ErrorKind::NotFound
+            }
+            Self::Resolve(err) | Self::ResolveTarget(_, err) if is_timeout(err) => {
+                io::ErrorKind::TimedOut
+            }
+            Self::Resolve(_) | Self::ResolveTarget(..) => io::ErrorKind::Other,
+            #[cfg(feature = "dnssec")]
+            Self::Dnssec(_) => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<HickoryToSocketAddrsError> for io::Error {
+    fn from(err: HickoryToSocketAddrsError) -> Self {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+#[cfg(feature = "tracing")]
+async fn traced<T>(
+    host: String,
+    port: u16,
+    fut: impl Future<Output = Result<T, HickoryToSocketAddrsError>>,
+    summary: impl Fn(&T) -> (usize, bool),
+) -> Result<T, HickoryToSocketAddrsError> {
+    use tracing::Instrument;
+
+    let span = tracing::info_span!(
+        "hickory_resolve",
+        %host,
+        port,
+        net.peer.name = %host,
+        net.peer.port = port,
+        dns.resolved_addresses = tracing::field::Empty,
+        dns.duration_ms = tracing::field::Empty,
+    );
+    async move {
+        let start = Instant::now();
+        let res = fut.await;
+        let span = tracing::Span::current();
+        span.record("dns.duration_ms", start.elapsed().as_millis() as u64);
+        if let Ok(res) = &res {
+            span.record("dns.resolved_addresses", summary(res).0);
+        }
+        match res.as_ref().map(&summary) {
+            Ok((addresses, from_cache)) => tracing::debug!(
+                addresses,
+                from_cache,
+                elapsed = ?start.elapsed(),
+                "DNS resolution succeeded"
+            ),
+            Err(err) => tracing::debug!(
+                kind = ?err.kind(),
+                error = %err,
+                elapsed = ?start.elapsed(),
+                "DNS resolution failed"
+            ),
+        }
+        res
+    }
+    .instrument(span)
+    .await
+}
+
+#[cfg(feature = "tracing")]
+async fn warn_partial(resolver: &dyn AnyResolver, lookup: &LookupIp) {
+    if resolver.options().ip_strategy != LookupIpStrategy::Ipv4AndIpv6 {
+        return;
+    }
+    let (missing, record_type) = match (
+        lookup.iter().any(|ip| ip.is_ipv4()),
+        lookup.iter().any(|ip| ip.is_ipv6()),
+    ) {
+        (true, false) => ("IPv6", RecordType::AAAA),
+        (false, true) => ("IPv4", RecordType::A),
+        _ => return,
+    };
+    // This is usually answered from the cache when the host has no such records.
+    let name = lookup.query().name();
+    if let Err(err) = resolver.lookup(name.clone(), record_type).await {
+        if !err.is_no_records_found() {
+            tracing::warn!(
+                %name,
+                family = missing,
+                error = %err,
+                "DNS resolution only partially succeeded"
+            );
+        }
+    }
+}
+
+fn weighted_order(mut records: Vec<SRV>, rng: &mut impl Rng) -> Vec<SRV> {
+    records.sort_by_key(SRV::priority);
+    let mut ordered = Vec::with_capacity(records.len());
+    for group in records.chunk_by(|a, b| a.priority() == b.priority()) {
+        // Records with a weight of 0 only come once the others were picked, instead of getting the
+        // small chance of being selected first RFC 2782 gives them.
+        let mut group = group.to_vec();
+        while !group.is_empty() {
+            let total = group.iter().map(|srv| u32::from(srv.weight())).sum::<u32>();
+            let idx = match total {
+                0 => 0,
+                _ => {
+                    let mut pick = rng.random_range(0..total);
+                    group
+                        .iter()
+                        .position(|srv| match pick.checked_sub(srv.weight().into()) {
+                            Some(rest) => {
+                                pick = rest;
+                                false
+                            }
+                            None => true,
+                        })
+                        .unwrap_or(0)
+                }
+            };
+            ordered.push(group.remove(idx));
+        }
+    }
+    ordered
+}
+
+/// Alternate between both families, starting with the one of the first address, which already is
+/// the preferred one when a preference is set.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let len = addrs.len();
+    let first_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (first, second): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+    let (mut first, mut second) = (first.into_iter(), second.into_iter());
+    let mut res = Vec::with_capacity(len);
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return res,
+            (first, second) => res.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Resolve several targets concurrently, returning their results in the same order
+///
+/// Targets created without an explicit resolver all share the process-wide default one.
+///
+/// ```rust
+/// use hickory_to_socket_addrs::HickoryToSocketAddrs;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let results = hickory_to_socket_addrs::resolve_many([
+///     HickoryToSocketAddrs::new("www.rust-lang.org", 443),
+///     HickoryToSocketAddrs::new("crates.io", 443),
+/// ])
+/// .await;
+/// # }
+/// ```
+pub async fn resolve_many<T: IntoName + Clone>(
+    targets: impl IntoIterator<Item = HickoryToSocketAddrs<T>>,
+) -> Vec<io::Result<Vec<SocketAddr>>> {
+    let targets = targets.into_iter().collect::<Vec<_>>();
+    future::join_all(
+        targets
+            .iter()
+            .map(|target| async move { Ok(target.resolve_all_async().await?) }),
+    )
+    .await
+}
+
+/// Resolve several targets like [`resolve_many`], with at most `max_concurrent` lookups in flight
+///
+/// The results are still returned in the same order as the targets. A limit of 0 is treated as 1.
+pub async fn resolve_many_limited<T: IntoName + Clone>(
+    targets: impl IntoIterator<Item = HickoryToSocketAddrs<T>>,
+    max_concurrent: usize,
+) -> Vec<io::Result<Vec<SocketAddr>>> {
+    use futures_util::{StreamExt, stream};
+
+    let targets = targets.into_iter().collect::<Vec<_>>();
+    stream::iter(
+        targets
+            .iter()
+            .map(|target| async move { Ok(target.resolve_all_async().await?) }),
+    )
+    .buffered(max_concurrent.max(1))
+    .collect()
+    .await
+}
+
+fn build_resolver(mut builder: ResolverBuilder<TokioConnectionProvider>) -> TokioResolver {
+    if cfg!(feature = "no-ipv6") {
+        builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4Only;
+    }
+    builder.build()
+}
+
+/// Drop the IPv6 addresses with the `no-ipv6` feature, which externally built resolvers may
+/// still return.
+fn without_ipv6(lookup: LookupIp) -> LookupIp {
+    if !cfg!(feature = "no-ipv6") {
+        return lookup;
+    }
+    let lookup = lookup.as_lookup();
+    let records = lookup
+        .records()
+        .iter()
+        .filter(|record| !matches!(record.data(), RData::AAAA(_)))
+        .cloned()
+        .collect();
+    Lookup::new_with_deadline(lookup.query().clone(), records, lookup.valid_until()).into()
+}
+
+/// The lookups performed through a [`Resolver`], whatever its connection provider.
+trait AnyResolver: fmt::Debug + Send + Sync {
+    /// A hash of the configuration of the resolver, keying its cache entries.
+    #[cfg(feature = "cache")]
+    fn config_hash(&self) -> u64;
+
+    fn options(&self) -> &ResolverOpts;
+
+    fn lookup(
+        &self,
+        name: Name,
+        record_type: RecordType,
+    ) -> BoxFuture<'_, Result<Lookup, ResolveError>>;
+
+    fn lookup_ip(&self, name: Name) -> BoxFuture<'_, Result<LookupIp, ResolveError>>;
+
+    fn lookup_ip_addr(&self, ip: IpAddr) -> BoxFuture<'_, Result<LookupIp, ResolveError>>;
+
+    /// The resolver itself if it uses the tokio connection provider, which allows building
+    /// similar ones.
+    fn as_tokio(&self) -> Option<&TokioResolver>;
+}
+
+/// A resolver, along with the hash of its configuration, computed once rather than on each cached
+/// lookup.
+#[derive(Debug)]
+struct HashedResolver<R> {
+    resolver: R,
+    #[cfg(feature = "cache")]
+    config_hash: u64,
+}
+
+impl<P: ConnectionProvider, R: Deref<Target = Resolver<P>>> HashedResolver<R> {
+    fn new(resolver: R) -> Self {
+        Self {
+            #[cfg(feature = "cache")]
+            config_hash: {
+                let mut hasher = std::hash::DefaultHasher::new();
+                format!("{:?} {:?}", resolver.config(), resolver.options()).hash(&mut hasher);
+                hasher.finish()
+            },
+            resolver,
+        }
+    }
+}
+
+impl<P, R> AnyResolver for HashedResolver<R>
+where
+    P: ConnectionProvider,
+    R: Deref<Target = Resolver<P>> + fmt::Debug + Send + Sync,
+{
+    #[cfg(feature = "cache")]
+    fn config_hash(&self) -> u64 {
+        self.config_hash
+    }
+
+    fn options(&self) -> &ResolverOpts {
+        self.resolver.options()
+    }
+
+    fn lookup(
+        &self,
+        name: Name,
+        record_type: RecordType,
+    ) -> BoxFuture<'_, Result<Lookup, ResolveError>> {
+        Box::pin(self.resolver.lookup(name, record_type))
+    }
+
+    fn lookup_ip(&self, name: Name) -> BoxFuture<'_, Result<LookupIp, ResolveError>> {
+        Box::pin(self.resolver.lookup_ip(name))
+    }
+
+    fn lookup_ip_addr(&self, ip: IpAddr) -> BoxFuture<'_, Result<LookupIp, ResolveError>> {
+        Box::pin(self.resolver.lookup_ip(ip.to_string()))
+    }
+
+    fn as_tokio(&self) -> Option<&TokioResolver> {
+        (&*self.resolver as &dyn Any).downcast_ref()
+    }
+}
+
+/// Erase the connection provider of the given resolver, hashing its configuration once.
+fn erase<P: ConnectionProvider>(resolver: impl Into<Arc<Resolver<P>>>) -> Arc<dyn AnyResolver> {
+    Arc::new(HashedResolver::new(resolver.into()))
+}
+
+/// Build a resolver with the same configuration as the given one, but with its own connections
+/// and cache.
+fn rebuild_resolver(resolver: &TokioResolver) -> TokioResolver {
+    build_resolver(
+        Resolver::builder_with_config(
+            resolver.config().clone(),
+            TokioConnectionProvider::default(),
+        )
+        .with_options(resolver.options().clone()),
+    )
+}
+
+static DEFAULT_RESOLVER: OnceLock<TokioResolver> = OnceLock::new();
+
+/// Configure the process-wide default resolver, used by all the lookups performed without an
+/// explicit resolver, including those of targets created through `FromStr`.
+///
+/// This can only be set once, before the first lookup going through the default resolver, which
+/// otherwise gets lazily built from the system configuration.
+///
+/// ```rust
+/// use hickory_to_socket_addrs::config::{ResolverConfig, ResolverOpts};
+///
+/// hickory_to_socket_addrs::init_global_resolver(
+///     ResolverConfig::cloudflare(),
+///     ResolverOpts::default(),
+/// )?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn init_global_resolver(
+    config: ResolverConfig,
+    options: ResolverOpts,
+) -> Result<(), HickoryToSocketAddrsError> {
+    let resolver = build_resolver(
+        Resolver::builder_with_config(config, TokioConnectionProvider::default())
+            .with_options(options),
+    );
+    DEFAULT_RESOLVER
+        .set(resolver)
+        .map_err(|_| HickoryToSocketAddrsError::AlreadyInitialized)
+}
+
+fn default_resolver() -> Result<&'static TokioResolver, ResolveError> {
+    if let Some(resolver) = DEFAULT_RESOLVER.get() {
+        return Ok(resolver);
+    }
+    let resolver = build_resolver(Resolver::builder_tokio()?);
+    Ok(DEFAULT_RESOLVER.get_or_init(|| resolver))
+}
+
+fn default_erased_resolver() -> Result<&'static dyn AnyResolver, ResolveError> {
+    static ERASED: OnceLock<HashedResolver<&'static TokioResolver>> = OnceLock::new();
+
+    if let Some(resolver) = ERASED.get() {
+        return Ok(resolver);
+    }
+    let resolver = default_resolver()?;
+    Ok(ERASED.get_or_init(|| HashedResolver::new(resolver)))
+}
+
+static RUNTIME_BUILDER: OnceLock<fn() -> io::Result<Runtime>> = OnceLock::new();
+
+/// Customize how the `tokio` runtime used when performing a lookup outside of any `tokio` context
+/// is built.
+///
+/// By default, a current thread runtime with all the drivers enabled is used. The built runtime
+/// must enable both the IO and the time drivers, which hickory relies on to send the queries and
+/// to time them out: lookups panic otherwise.
+///
+/// This runtime is lazily built once, then driven by a dedicated background thread for the
+/// remaining lifetime of the process. This can thus only be set once, before the first lookup
+/// needing it. No such runtime is ever built once disabled through [`set_implicit_runtime`].
+///
+/// ```rust
+/// hickory_to_socket_addrs::set_fallback_runtime_builder(|| {
+///     tokio::runtime::Builder::new_current_thread()
+///         .enable_all()
+///         .event_interval(31)
+///         .build()
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn set_fallback_runtime_builder(
+    builder: fn() -> io::Result<Runtime>,
+) -> Result<(), HickoryToSocketAddrsError> {
+    RUNTIME_BUILDER
+        .set(builder)
+        .map_err(|_| HickoryToSocketAddrsError::AlreadyInitialized)
+}
+
+static IMPLICIT_RUNTIME: AtomicBool = AtomicBool::new(true);
+
+/// Allow or forbid spawning a `tokio` runtime when performing a blocking lookup outside of a
+/// multi-thread `tokio` runtime.
+///
+/// This is allowed by default. Once forbidden, such lookups fail with an error of kind
+/// `io::ErrorKind::Unsupported` instead, so that strict applications keep control over the
+/// runtimes they run: use the async API, or call `to_socket_addrs` from a multi-thread runtime.
+pub fn set_implicit_runtime(enabled: bool) {
+    IMPLICIT_RUNTIME.store(enabled, atomic::Ordering::Relaxed);
+}
+
+static BLOCKING_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(Some(Duration::from_secs(30)));
+
+/// Set the ceiling applied to blocking lookups, such as `to_socket_addrs` called from a non-async
+/// program, or `None` to wait indefinitely.
+///
+/// The default ceiling is 30 seconds. It only applies to lookups without a timeout set through
+/// [`HickoryToSocketAddrs::with_timeout`], which is used instead otherwise. When it expires, an
+/// error of kind `io::ErrorKind::TimedOut` is returned.
+pub fn set_blocking_timeout(timeout: Option<Duration>) {
+    *BLOCKING_TIMEOUT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = timeout;
+}
+
+fn blocking_timeout() -> Option<Duration> {
+    *BLOCKING_TIMEOUT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn build_runtime() -> io::Result<Runtime> {
+    match RUNTIME_BUILDER.get() {
+        Some(builder) => builder(),
+        None => tokio::runtime::Builder::new_current_thread()
             .enable_all()
-            .build()?
-            .block_on(fut)
+            .build(),
+    }
+}
+
+fn background_runtime() -> io::Result<&'static Handle> {
+    if !IMPLICIT_RUNTIME.load(atomic::Ordering::Relaxed) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "blocking lookups need a multi-thread tokio runtime when the implicit one is disabled",
+        ));
+    }
+
+    static HANDLE: OnceLock<Handle> = OnceLock::new();
+    static INIT: Mutex<()> = Mutex::new(());
+
+    if let Some(handle) = HANDLE.get() {
+        return Ok(handle);
+    }
+    let _lock = INIT.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(handle) = HANDLE.get() {
+        return Ok(handle);
+    }
+    let runtime = build_runtime()?;
+    let handle = runtime.handle().clone();
+    thread::Builder::new()
+        .name("hickory-to-socket-addrs".into())
+        .spawn(move || runtime.block_on(pending::<()>()))?;
+    Ok(HANDLE.get_or_init(|| handle))
+}
+
+/// Spawn the future on the background runtime and wait for its output, or for the ceiling to
+/// expire, in which case `None` is returned and the future is dropped.
+fn block_on_background<T: Send + 'static>(
+    fut: impl Future<Output = T> + Send + 'static,
+    ceiling: Option<Duration>,
+) -> io::Result<Option<T>> {
+    let mut task = background_runtime()?.spawn(fut);
+    match park_on(&mut task, ceiling.map(|ceiling| Instant::now() + ceiling)) {
+        Some(Ok(output)) => Ok(Some(output)),
+        Some(Err(err)) => match err.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            Err(err) => Err(io::Error::other(err)),
+        },
+        None => {
+            task.abort();
+            Ok(None)
+        }
+    }
+}
+
+fn park_on<F: Future>(fut: F, deadline: Option<Instant>) -> Option<F::Output> {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return Some(res);
+        }
+        match deadline {
+            Some(deadline) => {
+                thread::park_timeout(deadline.checked_duration_since(Instant::now())?)
+            }
+            None => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srv(priority: u16, weight: u16, target: &str) -> SRV {
+        SRV::new(priority, weight, 443, Name::from_ascii(target).unwrap())
+    }
+
+    fn targets(records: &[SRV]) -> Vec<String> {
+        records.iter().map(|srv| srv.target().to_string()).collect()
+    }
+
+    #[test]
+    fn weighted_order_seeded() {
+        let records = vec![
+            srv(20, 0, "e"),
+            srv(10, 0, "a"),
+            srv(10, 5, "b"),
+            srv(20, 1, "f"),
+            srv(10, 0, "c"),
+            srv(10, 10, "d"),
+        ];
+        for seed in 0..100 {
+            let ordered = weighted_order(records.clone(), &mut StdRng::seed_from_u64(seed));
+            let again = weighted_order(records.clone(), &mut StdRng::seed_from_u64(seed));
+            assert_eq!(targets(&ordered), targets(&again));
+            let ordered = targets(&ordered);
+            assert!(ordered[..2] == ["b", "d"] || ordered[..2] == ["d", "b"]);
+            assert_eq!(ordered[2..], ["a", "c", "f", "e"]);
+        }
+    }
+
+    #[test]
+    fn interleave_starts_with_first_family() {
+        let v4 = |last| SocketAddr::from(([192, 0, 2, last], 443));
+        let v6 = |last| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, last], 443));
+        assert_eq!(
+            interleave(vec![v4(1), v4(2), v6(1), v6(2), v6(3)]),
+            [v4(1), v6(1), v4(2), v6(2), v6(3)]
+        );
+        assert_eq!(
+            interleave(vec![v6(1), v4(1), v4(2), v6(2)]),
+            [v6(1), v4(1), v6(2), v4(2)]
+        );
+        assert_eq!(interleave(Vec::new()), []);
     }
 }
@@ -0,0 +1,107 @@
+//! Resolution of `.local` names through one-shot multicast DNS queries (RFC 6762)
+
+use crate::HickoryToSocketAddrsError;
+use hickory_resolver::{
+    Name,
+    proto::{
+        op::{Message, MessageType, OpCode, Query},
+        rr::{RData, RecordType},
+    },
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+use tokio::{net::UdpSocket, time};
+
+const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+const MDNS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Whether the name belongs to the `.local` domain, handled through multicast DNS
+pub(crate) fn is_local(name: &Name) -> bool {
+    name.iter()
+        .next_back()
+        .is_some_and(|label| label.eq_ignore_ascii_case(b"local"))
+}
+
+/// Query the A and AAAA records of the given name, returning the addresses of the first responder
+///
+/// As the query is sent from an ephemeral port, responders answer through unicast. An empty list
+/// is returned if nobody answers within a second.
+pub(crate) async fn lookup(name: &Name) -> Result<Vec<IpAddr>, HickoryToSocketAddrsError> {
+    let mut query = Message::new();
+    query
+        .set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query);
+    for record_type in [RecordType::A, RecordType::AAAA] {
+        query.add_query(Query::query(name.clone(), record_type));
+    }
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|err| HickoryToSocketAddrsError::Mdns(err.to_string()))?;
+    socket
+        .send_to(&query.to_vec()?, MDNS_ADDR)
+        .await
+        .map_err(|err| HickoryToSocketAddrsError::Mdns(err.to_string()))?;
+    let mut buf = [0; 9000];
+    let recv = async {
+        loop {
+            let Ok(len) = socket.recv(&mut buf).await else {
+                return Vec::new();
+            };
+            let Ok(response) = Message::from_vec(&buf[..len]) else {
+                continue;
+            };
+            let ips = answer_ips(&response, name);
+            if !ips.is_empty() {
+                return ips;
+            }
+        }
+    };
+    Ok(time::timeout(MDNS_TIMEOUT, recv).await.unwrap_or_default())
+}
+
+/// The addresses of the given name found in the response
+fn answer_ips(response: &Message, name: &Name) -> Vec<IpAddr> {
+    // The names of the answers are fully qualified, which is part of the comparison.
+    let mut name = name.clone();
+    name.set_fqdn(true);
+    response
+        .answers()
+        .iter()
+        .chain(response.additionals())
+        .filter(|record| *record.name() == name)
+        .filter_map(|record| match record.data() {
+            RData::A(a) => Some(IpAddr::V4(a.0)),
+            RData::AAAA(aaaa) => Some(IpAddr::V6(aaaa.0)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::rr::{Record, rdata::A};
+
+    #[test]
+    fn answer_ips_of_queried_name() {
+        let mut response = Message::new();
+        response.add_answer(Record::from_rdata(
+            Name::from_ascii("printer.local.").unwrap(),
+            120,
+            RData::A(A::new(192, 168, 1, 10)),
+        ));
+        response.add_answer(Record::from_rdata(
+            Name::from_ascii("scanner.local.").unwrap(),
+            120,
+            RData::A(A::new(192, 168, 1, 11)),
+        ));
+        let name = Name::from_ascii("Printer.local").unwrap();
+        assert_eq!(
+            answer_ips(&response, &name),
+            [IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10))]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Utility lookups of other record types than A and AAAA, going through the process-wide default
+//! resolver.
+
+use crate::{HickoryToSocketAddrsError, default_resolver};
+use hickory_resolver::{
+    IntoName, Name,
+    proto::rr::{RData, RecordType},
+};
+use std::net::IpAddr;
+
+/// Perform a reverse (PTR) DNS lookup of the given IP address, returning the associated names
+///
+/// This goes through the process-wide default resolver.
+pub async fn reverse_lookup(ip: IpAddr) -> Result<Vec<String>, HickoryToSocketAddrsError> {
+    Ok(default_resolver()?
+        .reverse_lookup(ip)
+        .await?
+        .iter()
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Perform a TXT DNS lookup of the given name, returning the content of each record
+///
+/// The character strings of a record are concatenated, non UTF-8 data is replaced with
+/// `U+FFFD REPLACEMENT CHARACTER`. This goes through the process-wide default resolver.
+pub async fn lookup_txt(name: impl IntoName) -> Result<Vec<String>, HickoryToSocketAddrsError> {
+    Ok(default_resolver()?
+        .txt_lookup(name)
+        .await?
+        .iter()
+        .map(|txt| {
+            txt.txt_data()
+                .iter()
+                .map(|data| String::from_utf8_lossy(data))
+                .collect()
+        })
+        .collect())
+}
+
+/// Perform a CNAME DNS lookup of the given name, returning its target if it is an alias
+///
+/// This goes through the process-wide default resolver.
+pub async fn lookup_cname(name: impl IntoName) -> Result<Option<Name>, HickoryToSocketAddrsError> {
+    let lookup = match default_resolver()?.lookup(name, RecordType::CNAME).await {
+        Ok(lookup) => lookup,
+        Err(err) if err.is_no_records_found() => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(lookup.iter().find_map(|rdata| match rdata {
+        RData::CNAME(cname) => Some(cname.0.clone()),
+        _ => None,
+    }))
+}
+
+/// A NAPTR record, as used by SIP and ENUM for service discovery (RFC 3403)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Naptr {
+    /// The order in which the records must be processed, lowest first
+    pub order: u16,
+    /// The order in which records with the same `order` should be processed, lowest first
+    pub preference: u16,
+    /// The flags controlling the rewriting and interpretation of the record, such as `S` or `U`
+    pub flags: String,
+    /// The service parameters available through this rewrite path, such as `E2U+sip`
+    pub services: String,
+    /// The substitution expression applied to the original string
+    pub regexp: String,
+    /// The next domain name to query, when `regexp` is empty
+    pub replacement: Name,
+}
+
+/// Perform a NAPTR DNS lookup of the given name, returning the records sorted by order, then by
+/// preference
+///
+/// Non UTF-8 data is replaced with `U+FFFD REPLACEMENT CHARACTER`. This goes through the
+/// process-wide default resolver.
+pub async fn lookup_naptr(name: impl IntoName) -> Result<Vec<Naptr>, HickoryToSocketAddrsError> {
+    let mut records = default_resolver()?
+        .lookup(name, RecordType::NAPTR)
+        .await?
+        .iter()
+        .filter_map(|rdata| match rdata {
+            RData::NAPTR(naptr) => Some(Naptr {
+                order: naptr.order(),
+                preference: naptr.preference(),
+                flags: String::from_utf8_lossy(naptr.flags()).into_owned(),
+                services: String::from_utf8_lossy(naptr.services()).into_owned(),
+                regexp: String::from_utf8_lossy(naptr.regexp()).into_owned(),
+                replacement: naptr.replacement().clone(),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    records.sort_by_key(|naptr| (naptr.order, naptr.preference));
+    Ok(records)
+}
@@ -0,0 +1,50 @@
+use crate::{AnyResolver, HickoryToSocketAddrs, erase};
+use hickory_resolver::{Resolver, name_server::ConnectionProvider};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::sync::Arc;
+
+/// DNS resolver for `reqwest`, backed by `hickory-dns`
+///
+/// Plug it into a client through `ClientBuilder::dns_resolver`:
+///
+/// ```rust
+/// use hickory_to_socket_addrs::HickoryReqwestResolver;
+/// use std::sync::Arc;
+///
+/// let client = reqwest::Client::builder()
+///     .dns_resolver(Arc::new(HickoryReqwestResolver::new()))
+///     .build()?;
+/// # Ok::<(), reqwest::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HickoryReqwestResolver {
+    resolver: Option<Arc<dyn AnyResolver>>,
+}
+
+impl HickoryReqwestResolver {
+    /// Create a `HickoryReqwestResolver` using the process-wide default resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a `HickoryReqwestResolver` using the given resolver, whatever its connection provider.
+    pub fn with_resolver<P: ConnectionProvider>(resolver: Arc<Resolver<P>>) -> Self {
+        Self {
+            resolver: Some(erase(resolver)),
+        }
+    }
+}
+
+impl Resolve for HickoryReqwestResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        // reqwest sets the port itself
+        let target = HickoryToSocketAddrs {
+            resolver: self.resolver.clone(),
+            ..HickoryToSocketAddrs::new(name.as_str().to_owned(), 0)
+        };
+        Box::pin(async move {
+            let addrs: Addrs = Box::new(target.lookup().await?);
+            Ok(addrs)
+        })
+    }
+}
@@ -0,0 +1,299 @@
+//! Destination address selection, as described by RFC 6724
+
+use std::{
+    cmp::Reverse,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+};
+
+/// The default policy table: prefix, prefix length, precedence and label
+const POLICY_TABLE: [(u128, u32, u8, u8); 9] = [
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, 50, 0),
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000, 96, 35, 4),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 96, 1, 3),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000, 32, 5, 5),
+    (0x2002_0000_0000_0000_0000_0000_0000_0000, 16, 30, 2),
+    (0x3ffe_0000_0000_0000_0000_0000_0000_0000, 16, 1, 12),
+    (0xfec0_0000_0000_0000_0000_0000_0000_0000, 10, 1, 11),
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000, 7, 3, 13),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 0, 40, 1),
+];
+
+/// Sort the addresses by order of preference.
+///
+/// The source address used to reach each destination is determined by connecting an UDP socket to
+/// it, which doesn't send any packet. Rules 3, 4 and 7, which depend on information that isn't
+/// available here, are skipped.
+pub(crate) fn sort(addrs: &mut [SocketAddr]) {
+    sort_with(addrs, source_addr);
+}
+
+/// Sort the addresses by order of preference, given the source address used to reach each of them.
+fn sort_with(addrs: &mut [SocketAddr], source_addr: impl Fn(SocketAddr) -> Option<IpAddr>) {
+    addrs.sort_by_cached_key(|addr| {
+        let dst = to_ipv6(addr.ip());
+        // Rule 1: avoid unusable destinations.
+        let Some(src) = source_addr(*addr).map(to_ipv6) else {
+            return (true, false, false, Reverse(0), 0, Reverse(0));
+        };
+        let (precedence, label) = policy(dst);
+        (
+            false,
+            // Rule 2: prefer matching scope.
+            scope(dst) != scope(src),
+            // Rule 5: prefer matching label.
+            label != policy(src).1,
+            // Rule 6: prefer higher precedence.
+            Reverse(precedence),
+            // Rule 8: prefer smaller scope.
+            scope(dst),
+            // Rule 9: use longest matching prefix, within the 64 bits of the source subnet.
+            Reverse(match dst.to_ipv4_mapped() {
+                Some(_) => 0,
+                None => (u128::from(dst) ^ u128::from(src)).leading_zeros().min(64),
+            }),
+        )
+        // Rule 10: otherwise, leave the order unchanged, the sort being stable.
+    });
+}
+
+fn source_addr(mut addr: SocketAddr) -> Option<IpAddr> {
+    let unspecified = match addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    if addr.port() == 0 {
+        addr.set_port(1);
+    }
+    let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0)).ok()?;
+    socket.connect(addr).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn policy(ip: Ipv6Addr) -> (u8, u8) {
+    let bits = u128::from(ip);
+    POLICY_TABLE
+        .iter()
+        .find(|(prefix, len, ..)| *len == 0 || bits >> (128 - len) == prefix >> (128 - len))
+        .map_or((40, 1), |(_, _, precedence, label)| (*precedence, *label))
+}
+
+fn scope(ip: Ipv6Addr) -> u8 {
+    const LINK_LOCAL: u8 = 2;
+    const SITE_LOCAL: u8 = 5;
+    const GLOBAL: u8 = 14;
+
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return if ip.is_loopback() || ip.is_link_local() {
+            LINK_LOCAL
+        } else {
+            GLOBAL
+        };
+    }
+    let first = ip.segments()[0];
+    if ip.is_multicast() {
+        (first & 0x000f) as u8
+    } else if ip.is_loopback() || ip.is_unicast_link_local() {
+        LINK_LOCAL
+    } else if first & 0xffc0 == 0xfec0 {
+        SITE_LOCAL
+    } else {
+        GLOBAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sort the destinations, each one along with the source address used to reach it
+    fn sorted(candidates: &[(&str, Option<&str>)]) -> Vec<IpAddr> {
+        let sources = candidates
+            .iter()
+            .map(|(dst, src)| (dst.parse().unwrap(), src.map(|src| src.parse().unwrap())))
+            .collect::<Vec<(IpAddr, Option<IpAddr>)>>();
+        let mut addrs = sources
+            .iter()
+            .map(|(dst, _)| SocketAddr::new(*dst, 443))
+            .collect::<Vec<_>>();
+        sort_with(&mut addrs, |addr| {
+            sources
+                .iter()
+                .find(|(dst, _)| *dst == addr.ip())
+                .and_then(|(_, src)| *src)
+        });
+        addrs.into_iter().map(|addr| addr.ip()).collect()
+    }
+
+    fn ips(ips: &[&str]) -> Vec<IpAddr> {
+        ips.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    /// The destination address selection examples of RFC 6724, section 10.2
+    #[test]
+    fn rfc_examples() {
+        // Prefer matching scope.
+        assert_eq!(
+            sorted(&[
+                ("198.51.100.121", Some("169.254.13.78")),
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ]),
+            ips(&["2001:db8:1::1", "198.51.100.121"]),
+        );
+        assert_eq!(
+            sorted(&[
+                ("2001:db8:1::1", Some("fe80::1")),
+                ("198.51.100.121", Some("198.51.100.117")),
+            ]),
+            ips(&["198.51.100.121", "2001:db8:1::1"]),
+        );
+        // Prefer higher precedence.
+        assert_eq!(
+            sorted(&[
+                ("10.1.2.3", Some("10.1.2.4")),
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ]),
+            ips(&["2001:db8:1::1", "10.1.2.3"]),
+        );
+        // Prefer smaller scope.
+        assert_eq!(
+            sorted(&[
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+                ("fe80::1", Some("fe80::2")),
+            ]),
+            ips(&["fe80::1", "2001:db8:1::1"]),
+        );
+        // Longest matching prefix.
+        assert_eq!(
+            sorted(&[
+                ("2001:db8:3ffe::1", Some("2001:db8:3f44::2")),
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ]),
+            ips(&["2001:db8:1::1", "2001:db8:3ffe::1"]),
+        );
+        // Prefer matching label.
+        assert_eq!(
+            sorted(&[
+                ("2001:db8:1::1", Some("2002:c633:6401::2")),
+                ("2002:c633:6401::1", Some("2002:c633:6401::2")),
+            ]),
+            ips(&["2002:c633:6401::1", "2001:db8:1::1"]),
+        );
+        // Prefer higher precedence.
+        assert_eq!(
+            sorted(&[
+                ("2002:c633:6401::1", Some("2002:c633:6401::2")),
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ]),
+            ips(&["2001:db8:1::1", "2002:c633:6401::1"]),
+        );
+    }
+
+    #[test]
+    fn loopback_before_global() {
+        assert_eq!(
+            sorted(&[("2001:db8::1", Some("2001:db8::2")), ("::1", Some("::1"))]),
+            ips(&["::1", "2001:db8::1"]),
+        );
+    }
+
+    #[test]
+    fn native_ipv6_before_ipv4_mapped() {
+        assert_eq!(
+            sorted(&[
+                ("198.51.100.121", Some("198.51.100.117")),
+                ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ]),
+            ips(&["2001:db8:1::1", "198.51.100.121"]),
+        );
+    }
+
+    #[test]
+    fn global_before_ula() {
+        assert_eq!(
+            sorted(&[
+                ("fd00::1", Some("fd00::2")),
+                ("2001:db8::1", Some("2001:db8::2"))
+            ]),
+            ips(&["2001:db8::1", "fd00::1"]),
+        );
+        // Without a global source address, the label of the ULA destination matches.
+        assert_eq!(
+            sorted(&[
+                ("2001:db8::1", Some("fd00::2")),
+                ("fd00::1", Some("fd00::2"))
+            ]),
+            ips(&["fd00::1", "2001:db8::1"]),
+        );
+    }
+
+    #[test]
+    fn unusable_last() {
+        assert_eq!(
+            sorted(&[
+                ("2001:db8::1", None),
+                ("198.51.100.121", Some("198.51.100.117"))
+            ]),
+            ips(&["198.51.100.121", "2001:db8::1"]),
+        );
+    }
+
+    #[test]
+    fn stable_tie_break() {
+        let candidates = [
+            ("2001:db8:1::3", Some("2001:db8:1::2")),
+            ("2001:db8:1::1", Some("2001:db8:1::2")),
+            ("198.51.100.121", None),
+            ("198.51.100.122", None),
+        ];
+        assert_eq!(
+            sorted(&candidates),
+            ips(&[
+                "2001:db8:1::3",
+                "2001:db8:1::1",
+                "198.51.100.121",
+                "198.51.100.122"
+            ]),
+        );
+    }
+
+    #[test]
+    fn policy_table() {
+        for (ip, expected) in [
+            ("::1", (50, 0)),
+            ("::ffff:198.51.100.121", (35, 4)),
+            ("::198.51.100.121", (1, 3)),
+            ("2001::1", (5, 5)),
+            ("2002:c633:6401::1", (30, 2)),
+            ("3ffe::1", (1, 12)),
+            ("fec0::1", (1, 11)),
+            ("fd00::1", (3, 13)),
+            ("2001:db8::1", (40, 1)),
+        ] {
+            assert_eq!(policy(ip.parse().unwrap()), expected, "{ip}");
+        }
+    }
+
+    #[test]
+    fn scopes() {
+        for (ip, expected) in [
+            ("::1", 2),
+            ("fe80::1", 2),
+            ("ff02::1", 2),
+            ("ff05::1", 5),
+            ("fec0::1", 5),
+            ("2001:db8::1", 14),
+            ("::ffff:127.0.0.1", 2),
+            ("::ffff:169.254.13.78", 2),
+            ("::ffff:10.1.2.3", 14),
+        ] {
+            assert_eq!(scope(ip.parse().unwrap()), expected, "{ip}");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{HickoryToSocketAddrs, HickoryToSocketAddrsError};
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+/// Generic `tower` service resolving [`HickoryToSocketAddrs`] targets into their addresses
+///
+/// Each target is resolved through its own resolver, which is the process-wide default one unless
+/// it was built with a custom one. This allows layering `tower` middlewares, such as retries or
+/// rate limits, around the DNS resolution.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HickoryLookupService;
+
+impl HickoryLookupService {
+    /// Create a `HickoryLookupService`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Service<HickoryToSocketAddrs<String>> for HickoryLookupService {
+    type Response = Vec<SocketAddr>;
+    type Error = HickoryToSocketAddrsError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: HickoryToSocketAddrs<String>) -> Self::Future {
+        Box::pin(async move { target.resolve_all_async().await })
+    }
+}
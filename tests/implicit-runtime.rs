@@ -0,0 +1,24 @@
+use hickory_to_socket_addrs::HickoryToSocketAddrs;
+use std::{io::ErrorKind, net::ToSocketAddrs};
+
+#[test]
+fn blocking_without_implicit_runtime() {
+    hickory_to_socket_addrs::set_implicit_runtime(false);
+    let target = HickoryToSocketAddrs::new("www.rust-lang.org", 443);
+    assert_eq!(
+        target.to_socket_addrs().unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let err = runtime.block_on(async { target.to_socket_addrs().unwrap_err() });
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    // IP addresses don't need any lookup.
+    assert!(
+        HickoryToSocketAddrs::new("127.0.0.1", 443)
+            .to_socket_addrs()
+            .is_ok()
+    );
+}
@@ -0,0 +1,454 @@
+use hickory_resolver::{
+    Resolver,
+    name_server::{ConnectionProvider, TokioConnectionProvider},
+};
+use hickory_to_socket_addrs::{
+    AddressFamily, HickoryToSocketAddrs, HickoryToSocketAddrsError,
+    config::{
+        LookupIpStrategy, NameServerConfig, NameServerConfigGroup, ResolverConfig, ResolverOpts,
+    },
+};
+use std::{
+    io::{self, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, ToSocketAddrs, UdpSocket},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+/// Spawn a minimal DNS server on loopback, answering every A query with 127.0.0.1 and every AAAA
+/// query with ::1
+fn stub_server() -> SocketAddr {
+    stub_server_answering(&[Ipv4Addr::LOCALHOST])
+}
+
+/// Spawn a minimal DNS server on loopback, answering every A query with the given addresses, in
+/// order, and every AAAA query with ::1
+fn stub_server_answering(ips: &[Ipv4Addr]) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    let ips = ips.to_vec();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+            let query = &buf[..len];
+            let mut end = 12;
+            while query[end] != 0 {
+                end += usize::from(query[end]) + 1;
+            }
+            end += 5;
+            let rdatas = match query[end - 4..end - 2] {
+                [0, 1] => ips.iter().map(|ip| ip.octets().to_vec()).collect(),
+                [0, 28] => vec![Ipv6Addr::LOCALHOST.octets().to_vec()],
+                _ => Vec::new(),
+            };
+            let mut response = Vec::from(&query[..2]);
+            response.extend([0x81, 0x80, 0, 1, 0, rdatas.len() as u8, 0, 0, 0, 0]);
+            response.extend(&query[12..end]);
+            for rdata in rdatas {
+                response.extend([0xc0, 12]);
+                response.extend(&query[end - 4..end]);
+                response.extend([0, 0, 0, 60, 0, rdata.len() as u8]);
+                response.extend(rdata);
+            }
+            let _ = socket.send_to(&response, peer);
+        }
+    });
+    addr
+}
+
+#[test]
+fn resolve_with_bind_addr() {
+    let addrs = HickoryToSocketAddrs::builder("stub.test", 443)
+        .nameservers(&[stub_server()])
+        .bind_addr(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .build()
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(addrs, ["127.0.0.1:443".parse::<SocketAddr>().unwrap()]);
+}
+
+#[test]
+fn resolve_with_nameserver_port() {
+    let nameserver = stub_server();
+    assert_ne!(nameserver.port(), 53);
+    let addrs = HickoryToSocketAddrs::with_nameservers("stub.test", 80, &[nameserver])
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(addrs, ["127.0.0.1:80".parse::<SocketAddr>().unwrap()]);
+}
+
+#[tokio::test]
+async fn connect_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stream = HickoryToSocketAddrs::new("127.0.0.1", addr.port())
+        .connect_tcp()
+        .await
+        .unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+}
+
+#[tokio::test]
+async fn resolve_blocking_after_async() {
+    let config = ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from_ips_clear(
+            &[Ipv4Addr::LOCALHOST.into()],
+            stub_server().port(),
+            true,
+        ),
+    );
+    hickory_to_socket_addrs::init_global_resolver(config, ResolverOpts::default()).unwrap();
+    let timeout = Duration::from_secs(2);
+    let target = HickoryToSocketAddrs::new("async.stub.test", 80).with_timeout(timeout);
+    assert_eq!(target.lookup().await.unwrap().count(), 1);
+    // Use another name, so that this isn't answered from the cache.
+    let target = HickoryToSocketAddrs::new("blocking.stub.test", 80).with_timeout(timeout);
+    assert_eq!(target.to_socket_addrs().unwrap().count(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_timeout_on_multi_thread_runtime() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    hickory_to_socket_addrs::set_blocking_timeout(Some(Duration::from_millis(500)));
+    let timeouts = Arc::new(AtomicUsize::new(0));
+    let counter = timeouts.clone();
+    let target =
+        HickoryToSocketAddrs::with_nameservers("silent.test", 80, &[silent.local_addr().unwrap()])
+            .unwrap()
+            .on_complete(move |_, outcome, _| {
+                if let Err(HickoryToSocketAddrsError::Timeout(_)) = outcome {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+    let err = target.to_socket_addrs().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    // Same outside of any runtime, where the lookup runs on the background one.
+    let err = thread::scope(|s| s.spawn(|| target.to_socket_addrs()).join().unwrap()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(timeouts.load(Ordering::SeqCst), 2);
+}
+
+/// Delegate to the tokio connection provider, counting the connections
+#[derive(Clone, Default)]
+struct CountingProvider(TokioConnectionProvider, Arc<AtomicUsize>);
+
+impl ConnectionProvider for CountingProvider {
+    type Conn = <TokioConnectionProvider as ConnectionProvider>::Conn;
+    type FutureConn = <TokioConnectionProvider as ConnectionProvider>::FutureConn;
+    type RuntimeProvider = <TokioConnectionProvider as ConnectionProvider>::RuntimeProvider;
+
+    fn new_connection(
+        &self,
+        config: &NameServerConfig,
+        options: &ResolverOpts,
+    ) -> io::Result<Self::FutureConn> {
+        self.1.fetch_add(1, Ordering::SeqCst);
+        self.0.new_connection(config, options)
+    }
+}
+
+#[tokio::test]
+async fn resolve_with_custom_connection_provider() {
+    let config = ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from_ips_clear(
+            &[Ipv4Addr::LOCALHOST.into()],
+            stub_server().port(),
+            true,
+        ),
+    );
+    let provider = CountingProvider::default();
+    let resolver = Resolver::builder_with_config(config, provider.clone()).build();
+    let target = HickoryToSocketAddrs::with_resolver("provider.stub.test", 80, resolver);
+    assert_eq!(target.lookup().await.unwrap().count(), 1);
+    assert!(provider.1.load(Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn resolver_timeout() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_millis(200);
+    options.attempts = 1;
+    let err = HickoryToSocketAddrs::builder("silent.test", 80)
+        .nameservers(&[silent.local_addr().unwrap()])
+        .options(options)
+        .build()
+        .unwrap()
+        .lookup()
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[tokio::test]
+async fn lookup_variants_report_target() {
+    let stub = stub_server();
+    let completed = Arc::new(AtomicUsize::new(0));
+    let counter = completed.clone();
+    let target = HickoryToSocketAddrs::with_nameservers("variants.stub.test", 80, &[stub])
+        .unwrap()
+        .on_complete(move |_, outcome, _| {
+            assert_eq!(outcome.unwrap(), 1);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+    assert_eq!(target.lookup_ips().await.unwrap().count(), 1);
+    assert_eq!(target.lookup_raw().await.unwrap().iter().count(), 1);
+    assert_eq!(target.lookup_with_ttl().await.unwrap().len(), 1);
+    assert_eq!(target.lookup_detailed_iter().await.unwrap().count(), 1);
+    assert_eq!(target.lookup_with_canonical().await.unwrap().0.count(), 1);
+    assert_eq!(completed.load(Ordering::SeqCst), 5);
+
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_millis(200);
+    options.attempts = 1;
+    let target = HickoryToSocketAddrs::builder("silent.test", 80)
+        .nameservers(&[silent.local_addr().unwrap()])
+        .options(options)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        target.lookup_ips().await.map(|_| ()),
+        Err(HickoryToSocketAddrsError::ResolveTarget(target, _)) if target == "silent.test:80"
+    ));
+}
+
+#[tokio::test]
+async fn preferred_family_or_wait() {
+    let stub = stub_server();
+    let target =
+        HickoryToSocketAddrs::with_nameservers("preferred.stub.test", 80, &[stub]).unwrap();
+    let delay = Duration::from_secs(1);
+    let addrs = target
+        .resolve_preferred_family_or_wait(delay)
+        .await
+        .unwrap();
+    let expected = match cfg!(feature = "no-ipv6") {
+        true => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        false => IpAddr::V6(Ipv6Addr::LOCALHOST),
+    };
+    assert_eq!(addrs.collect::<Vec<_>>(), [SocketAddr::new(expected, 80)]);
+    let addrs = target
+        .clone()
+        .prefer_family(AddressFamily::V4Only)
+        .resolve_preferred_family_or_wait(delay)
+        .await
+        .unwrap();
+    assert_eq!(
+        addrs.collect::<Vec<_>>(),
+        [SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 80)]
+    );
+    let addrs = target
+        .clone()
+        .address_family(AddressFamily::V4Only)
+        .resolve_preferred_family_or_wait(delay)
+        .await
+        .unwrap();
+    assert!(addrs.into_iter().all(|addr| addr.is_ipv4()));
+    let target = HickoryToSocketAddrs::with_nameservers("preferred.stub.test", 0, &[stub])
+        .unwrap()
+        .reject_zero_port(true);
+    assert!(matches!(
+        target.resolve_preferred_family_or_wait(delay).await,
+        Err(HickoryToSocketAddrsError::InvalidPort)
+    ));
+}
+
+#[test]
+fn no_std_fallback_on_timeout() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_millis(200);
+    options.attempts = 1;
+    // The system resolver accepts this shorthand for 127.0.0.1, hickory looks it up as a name.
+    let err = HickoryToSocketAddrs::builder("127.1", 80)
+        .nameservers(&[silent.local_addr().unwrap()])
+        .options(options)
+        .build()
+        .unwrap()
+        .with_std_fallback(true)
+        .to_socket_addrs()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn cache_per_resolver() {
+    for ip in [Ipv4Addr::new(127, 0, 0, 2), Ipv4Addr::new(127, 0, 0, 3)] {
+        let addrs = HickoryToSocketAddrs::with_nameservers(
+            "cached.test",
+            80,
+            &[stub_server_answering(&[ip])],
+        )
+        .unwrap()
+        .cached(true)
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+        assert_eq!(addrs, [SocketAddr::new(ip.into(), 80)]);
+    }
+}
+
+#[cfg(feature = "no-ipv6")]
+#[tokio::test]
+async fn no_ipv6_with_external_resolver() {
+    use hickory_resolver::{
+        Resolver, config::LookupIpStrategy, name_server::TokioConnectionProvider,
+    };
+
+    let config = ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        NameServerConfigGroup::from_ips_clear(
+            &[Ipv4Addr::LOCALHOST.into()],
+            stub_server().port(),
+            true,
+        ),
+    );
+    let mut builder = Resolver::builder_with_config(config, TokioConnectionProvider::default());
+    builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+    let target = HickoryToSocketAddrs::with_resolver("dual.stub.test", 80, builder.build());
+    let raw = target.lookup_raw().await.unwrap();
+    assert!(raw.iter().all(|ip| ip.is_ipv4()));
+    assert!(target.lookup_ips().await.unwrap().all(|ip| ip.is_ipv4()));
+    let with_ttl = target.lookup_with_ttl().await.unwrap();
+    assert!(with_ttl.iter().all(|(addr, _)| addr.is_ipv4()));
+    let mut detailed = target.lookup_detailed_iter().await.unwrap();
+    assert!(detailed.all(|detailed| detailed.addr.is_ipv4()));
+    assert_eq!(target.lookup().await.unwrap().len(), 1);
+}
+
+/// The addresses answered by [`stub_target`], in order, including a duplicate
+const STUB_IPS: [Ipv4Addr; 5] = [
+    Ipv4Addr::new(127, 0, 0, 1),
+    Ipv4Addr::new(127, 0, 0, 2),
+    Ipv4Addr::new(127, 0, 0, 1),
+    Ipv4Addr::new(127, 0, 0, 3),
+    Ipv4Addr::new(127, 0, 0, 4),
+];
+
+/// Target a stub server answering [`STUB_IPS`] and ::1, queried with the given strategy
+fn stub_target(host: &str, ip_strategy: LookupIpStrategy) -> HickoryToSocketAddrs<String> {
+    let mut options = ResolverOpts::default();
+    options.ip_strategy = ip_strategy;
+    HickoryToSocketAddrs::builder(host.to_owned(), 80)
+        .nameservers(&[stub_server_answering(&STUB_IPS)])
+        .options(options)
+        .build()
+        .unwrap()
+}
+
+fn v4(last: u8) -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::new(127, 0, 0, last).into(), 80)
+}
+
+fn v6() -> SocketAddr {
+    SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 80)
+}
+
+#[tokio::test]
+async fn stub_answers_in_order() {
+    let target = stub_target("order.stub.test", LookupIpStrategy::Ipv4Only);
+    let addrs = target.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(addrs, [v4(1), v4(2), v4(1), v4(3), v4(4)]);
+}
+
+#[tokio::test]
+async fn shuffle_with_seed_is_reproducible() {
+    let target = stub_target("shuffle.stub.test", LookupIpStrategy::Ipv4Only);
+    let shuffled = target.clone().shuffle_with_seed(42);
+    let first = shuffled.lookup().await.unwrap().collect::<Vec<_>>();
+    let second = shuffled.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(first, second);
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(sorted, [v4(1), v4(1), v4(2), v4(3), v4(4)]);
+    let other = target.shuffle_with_seed(7).lookup().await.unwrap();
+    assert_ne!(other.collect::<Vec<_>>(), first);
+}
+
+#[tokio::test]
+async fn address_family_filters() {
+    let target = stub_target("family.stub.test", LookupIpStrategy::Ipv4AndIpv6);
+    let addrs = target.clone().address_family(AddressFamily::V4Only);
+    let addrs = addrs.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(addrs, [v4(1), v4(2), v4(1), v4(3), v4(4)]);
+    let addrs = target.address_family(AddressFamily::V6Only);
+    let addrs = addrs.lookup().await.unwrap().collect::<Vec<_>>();
+    match cfg!(feature = "no-ipv6") {
+        true => assert_eq!(addrs, []),
+        false => assert_eq!(addrs, [v6()]),
+    }
+}
+
+#[tokio::test]
+async fn dedup_preserves_first_seen_order() {
+    let target = stub_target("dedup.stub.test", LookupIpStrategy::Ipv4Only).dedup(true);
+    let addrs = target.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(addrs, [v4(1), v4(2), v4(3), v4(4)]);
+}
+
+#[tokio::test]
+async fn max_results_after_dedup() {
+    let target = stub_target("max.stub.test", LookupIpStrategy::Ipv4Only)
+        .dedup(true)
+        .max_results(3);
+    let addrs = target.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(addrs, [v4(1), v4(2), v4(3)]);
+}
+
+#[tokio::test]
+async fn error_on_empty_after_filtering() {
+    let target = stub_target("empty.stub.test", LookupIpStrategy::Ipv4Only)
+        .address_family(AddressFamily::V6Only);
+    assert_eq!(target.lookup().await.unwrap().count(), 0);
+    assert!(matches!(
+        target.error_on_empty(true).lookup().await,
+        Err(HickoryToSocketAddrsError::NoAddresses(host)) if host == "empty.stub.test"
+    ));
+}
+
+#[tokio::test]
+async fn prefer_family_orders() {
+    let target = stub_target("prefer.stub.test", LookupIpStrategy::Ipv4AndIpv6).dedup(true);
+    let v4s = [v4(1), v4(2), v4(3), v4(4)];
+    let addrs = target.clone().prefer_family(AddressFamily::V4Only);
+    let addrs = addrs.lookup().await.unwrap().collect::<Vec<_>>();
+    let v6s = if cfg!(feature = "no-ipv6") {
+        Vec::new()
+    } else {
+        vec![v6()]
+    };
+    assert_eq!(addrs, [&v4s[..], &v6s].concat());
+    let addrs = target.prefer_family(AddressFamily::V6Only);
+    let addrs = addrs.lookup().await.unwrap().collect::<Vec<_>>();
+    assert_eq!(addrs, [&v6s, &v4s[..]].concat());
+}
+
+#[tokio::test]
+async fn round_robin_rotates() {
+    let target = stub_target("rotate.stub.test", LookupIpStrategy::Ipv4Only)
+        .dedup(true)
+        .round_robin(true);
+    let mut expected = vec![v4(1), v4(2), v4(3), v4(4)];
+    for _ in 0..5 {
+        assert_eq!(target.lookup().await.unwrap().collect::<Vec<_>>(), expected);
+        expected.rotate_left(1);
+    }
+    // Clones share the rotation.
+    let clone = target.clone();
+    assert_eq!(clone.lookup().await.unwrap().collect::<Vec<_>>(), expected);
+}
@@ -0,0 +1,214 @@
+use hickory_to_socket_addrs::{HickoryToSocketAddrs, HickoryToSocketAddrsError, Name};
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+};
+
+#[test]
+fn parse_ipv6_literal() {
+    assert!(HickoryToSocketAddrs::from_str("[::1]:443").is_ok());
+    assert!(HickoryToSocketAddrs::from_str("[2001:db8::1]:8080").is_ok());
+}
+
+#[cfg(not(feature = "no-ipv6"))]
+#[test]
+fn parse_ipv6_zone() {
+    let addrs = HickoryToSocketAddrs::from_str("[fe80::1%3]:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(addrs, ["[fe80::1%3]:443".parse::<SocketAddr>().unwrap()]);
+    let target = HickoryToSocketAddrs::parse_with_default_port("[fe80::1%3]", 443).unwrap();
+    assert_eq!(target.to_socket_addrs().unwrap().collect::<Vec<_>>(), addrs);
+    // The scope ID only applies to link-local addresses.
+    let addrs = HickoryToSocketAddrs::from_str("[2001:db8::1%3]:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(addrs, ["[2001:db8::1]:443".parse::<SocketAddr>().unwrap()]);
+    assert!(matches!(
+        HickoryToSocketAddrs::from_str("[fe80::1%no-such-interface]:443"),
+        Err(HickoryToSocketAddrsError::InvalidZone(zone)) if zone == "no-such-interface"
+    ));
+}
+
+#[cfg(all(target_os = "linux", not(feature = "no-ipv6")))]
+#[test]
+fn parse_ipv6_zone_interface_name() {
+    let mut addrs = HickoryToSocketAddrs::from_str("[fe80::1%lo]:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap();
+    match addrs.next() {
+        Some(SocketAddr::V6(addr)) => assert_ne!(addr.scope_id(), 0),
+        addr => panic!("unexpected address: {addr:?}"),
+    }
+}
+
+#[test]
+fn parse_invalid() {
+    assert!(matches!(
+        HickoryToSocketAddrs::from_str("www.rust-lang.org"),
+        Err(HickoryToSocketAddrsError::MissingPort)
+    ));
+    assert!(matches!(
+        HickoryToSocketAddrs::from_str("2001:db8::1:8080"),
+        Err(HickoryToSocketAddrsError::MissingPort)
+    ));
+    assert!(matches!(
+        HickoryToSocketAddrs::from_str("[::1]"),
+        Err(HickoryToSocketAddrsError::MissingPort)
+    ));
+    assert!(matches!(
+        HickoryToSocketAddrs::from_str("www.rust-lang.org:https"),
+        Err(HickoryToSocketAddrsError::InvalidPort)
+    ));
+}
+
+#[test]
+fn parse_with_default_port() {
+    assert!(HickoryToSocketAddrs::parse_with_default_port("www.rust-lang.org", 443).is_ok());
+    assert!(HickoryToSocketAddrs::parse_with_default_port("[::1]", 443).is_ok());
+    assert!(matches!(
+        HickoryToSocketAddrs::parse_with_default_port("www.rust-lang.org:https", 443),
+        Err(HickoryToSocketAddrsError::InvalidPort)
+    ));
+}
+
+#[test]
+fn parse_list() {
+    assert_eq!(
+        HickoryToSocketAddrs::parse_list("www.rust-lang.org:443, [::1]:443").unwrap(),
+        [
+            HickoryToSocketAddrs::new("www.rust-lang.org".to_owned(), 443),
+            HickoryToSocketAddrs::new("::1".to_owned(), 443),
+        ]
+    );
+    assert!(matches!(
+        HickoryToSocketAddrs::parse_list("www.rust-lang.org:443,crates.io"),
+        Err(HickoryToSocketAddrsError::InvalidEntry(entry, _)) if entry == "crates.io"
+    ));
+}
+
+#[test]
+fn try_from_tuple() {
+    assert_eq!(
+        HickoryToSocketAddrs::try_from(("www.rust-lang.org", 443)).unwrap(),
+        HickoryToSocketAddrs::new("www.rust-lang.org".to_owned(), 443)
+    );
+    assert!(matches!(
+        HickoryToSocketAddrs::try_from(("", 443)),
+        Err(HickoryToSocketAddrsError::EmptyHost)
+    ));
+    let err = HickoryToSocketAddrs::from_str(":443").unwrap_err();
+    assert_eq!(err.to_string(), "empty host");
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(matches!(
+        HickoryToSocketAddrs::try_new("", 443),
+        Err(HickoryToSocketAddrsError::EmptyHost)
+    ));
+    assert!(HickoryToSocketAddrs::try_new("::1", 443).is_ok());
+    assert!(HickoryToSocketAddrs::try_new("www.rust-lang.org\n", 443).is_err());
+    let addr = "[::1]:443".parse::<SocketAddr>().unwrap();
+    let addrs = HickoryToSocketAddrs::from((addr.ip(), addr.port()))
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    // IPv6 literals are filtered out like any other IPv6 address with the no-ipv6 feature.
+    match cfg!(feature = "no-ipv6") {
+        true => assert_eq!(addrs, []),
+        false => assert_eq!(addrs, [addr]),
+    }
+}
+
+#[test]
+fn reject_zero_port() {
+    let target = HickoryToSocketAddrs::from_str("127.0.0.1:0").unwrap();
+    assert!(target.to_socket_addrs().is_ok());
+    let err = target.reject_zero_port(true).to_socket_addrs().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn with_origin_too_long() {
+    let label = "a".repeat(63);
+    let origin = Name::from_ascii([label.as_str(); 3].join(".")).unwrap();
+    let err = HickoryToSocketAddrs::new(label, 443)
+        .with_origin(origin)
+        .to_socket_addrs()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn map_port() {
+    let addrs = HickoryToSocketAddrs::from_str("127.0.0.1:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap();
+    let addrs = addrs.with_port(80).map_port(|addr| addr.port() + 1);
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(
+        addrs.clone().collect::<Vec<_>>(),
+        addrs.clone().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        addrs.collect::<Vec<_>>(),
+        ["127.0.0.1:81".parse::<SocketAddr>().unwrap()]
+    );
+}
+
+#[test]
+fn parse_eq() {
+    assert_eq!(
+        HickoryToSocketAddrs::from_str("[::1]:443").unwrap(),
+        HickoryToSocketAddrs::new("::1".to_owned(), 443)
+    );
+    assert_eq!(
+        HickoryToSocketAddrs::parse_with_default_port("www.rust-lang.org", 443).unwrap(),
+        HickoryToSocketAddrs::new("www.rust-lang.org".to_owned(), 443)
+    );
+}
+
+#[test]
+fn sort() {
+    let mut targets =
+        HickoryToSocketAddrs::parse_list("b.example:80, a.example:443, a.example:80").unwrap();
+    targets.sort();
+    assert_eq!(
+        targets.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["a.example:80", "a.example:443", "b.example:80"]
+    );
+}
+
+#[test]
+fn display() {
+    for s in [
+        "www.rust-lang.org:443",
+        "127.0.0.1:80",
+        "[::1]:443",
+        "[fe80::1%3]:443",
+    ] {
+        assert_eq!(HickoryToSocketAddrs::from_str(s).unwrap().to_string(), s);
+    }
+}
+
+#[test]
+fn init_global_resolver_twice() {
+    use hickory_to_socket_addrs::config::{ResolverConfig, ResolverOpts};
+
+    let _ = hickory_to_socket_addrs::init_global_resolver(
+        ResolverConfig::default(),
+        ResolverOpts::default(),
+    );
+    assert!(matches!(
+        hickory_to_socket_addrs::init_global_resolver(
+            ResolverConfig::default(),
+            ResolverOpts::default()
+        ),
+        Err(HickoryToSocketAddrsError::AlreadyInitialized)
+    ));
+}
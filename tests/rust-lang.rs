@@ -1,5 +1,9 @@
-use hickory_to_socket_addrs::HickoryToSocketAddrs;
-use std::{net::ToSocketAddrs, str::FromStr};
+use hickory_to_socket_addrs::{HickoryToSocketAddrs, config::ResolverConfig};
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+};
 
 #[test]
 fn resolve() {
@@ -11,3 +15,122 @@ fn resolve() {
         println!("{addr:?}");
     }
 }
+
+#[test]
+fn resolve_with_config() {
+    for addr in HickoryToSocketAddrs::builder("www.rust-lang.org", 443)
+        .config(ResolverConfig::cloudflare())
+        .build()
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+    {
+        println!("{addr:?}");
+    }
+}
+
+#[test]
+fn resolve_with_attempts() {
+    for attempts in [1, 3] {
+        let addrs = HickoryToSocketAddrs::builder("www.rust-lang.org", 443)
+            .attempts(attempts)
+            .build()
+            .unwrap()
+            .to_socket_addrs()
+            .unwrap();
+        assert!(addrs.count() > 0);
+    }
+}
+
+#[test]
+fn resolve_ip_literal() {
+    let addrs = HickoryToSocketAddrs::from_str("[::1]:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(addrs, ["[::1]:443".parse::<SocketAddr>().unwrap()]);
+}
+
+#[test]
+fn resolve_invalid_name() {
+    let err = HickoryToSocketAddrs::new("", 443)
+        .to_socket_addrs()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn resolve_nxdomain() {
+    let err = HickoryToSocketAddrs::new("does-not-exist.invalid", 443)
+        .to_socket_addrs()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn resolve_nxdomain_query() {
+    let err = HickoryToSocketAddrs::new("does-not-exist.invalid", 443)
+        .absolute(true)
+        .lookup()
+        .await
+        .unwrap_err();
+    let query = err.query().unwrap();
+    assert_eq!(query.name().to_string(), "does-not-exist.invalid.");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resolve_from_multi_thread_runtime() {
+    for addr in HickoryToSocketAddrs::new("www.rust-lang.org", 443)
+        .to_socket_addrs()
+        .unwrap()
+    {
+        println!("{addr:?}");
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn resolve_from_current_thread_runtime() {
+    for addr in HickoryToSocketAddrs::new("www.rust-lang.org", 443)
+        .to_socket_addrs()
+        .unwrap()
+    {
+        println!("{addr:?}");
+    }
+}
+
+#[test]
+fn resolve_idn() {
+    for addr in HickoryToSocketAddrs::from_str("münchen.de:443")
+        .unwrap()
+        .to_socket_addrs()
+        .unwrap()
+    {
+        println!("{addr:?}");
+    }
+}
+
+#[tokio::test]
+async fn resolve_many() {
+    let results = hickory_to_socket_addrs::resolve_many([
+        HickoryToSocketAddrs::new("www.rust-lang.org", 443),
+        HickoryToSocketAddrs::new("127.0.0.1", 80),
+    ])
+    .await;
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[1].as_ref().unwrap(),
+        &["127.0.0.1:80".parse::<SocketAddr>().unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn resolve_many_limited() {
+    let targets = (1..=4).map(|port| HickoryToSocketAddrs::new("127.0.0.1", port));
+    let results = hickory_to_socket_addrs::resolve_many_limited(targets, 2).await;
+    let ports = results
+        .into_iter()
+        .map(|res| res.unwrap()[0].port())
+        .collect::<Vec<_>>();
+    assert_eq!(ports, [1, 2, 3, 4]);
+}